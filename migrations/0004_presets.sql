@@ -0,0 +1,6 @@
+CREATE TABLE IF NOT EXISTS presets (
+    name       TEXT    PRIMARY KEY,
+    work_secs  INTEGER NOT NULL,
+    break_secs INTEGER NOT NULL,
+    created_at TEXT    NOT NULL
+);
@@ -0,0 +1 @@
+ALTER TABLE presets ADD COLUMN long_break_secs INTEGER;
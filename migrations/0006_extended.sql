@@ -0,0 +1,23 @@
+ALTER TABLE events ADD COLUMN extended_secs INTEGER;
+
+-- Extensions are not lifecycle events, so they never become a session's final_event
+DROP VIEW IF EXISTS sessions;
+
+CREATE VIEW sessions AS
+SELECT
+    e.session_id,
+    (SELECT timer_type FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS timer_type,
+    (SELECT task FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS task,
+    MIN(e.at)                          AS started_at,
+    MAX(e.at)                          AS ended_at,
+    MAX(e.work_secs)                   AS work_secs,
+    COALESCE(SUM(e.extended_secs), 0)  AS extended_secs,
+    (SELECT event_type FROM events
+     WHERE session_id = e.session_id AND event_type <> 'Extended'
+     ORDER BY at DESC, id DESC LIMIT 1) AS final_event
+FROM events e
+GROUP BY e.session_id;
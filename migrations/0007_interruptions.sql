@@ -0,0 +1,27 @@
+ALTER TABLE events ADD COLUMN interruption_kind TEXT;
+ALTER TABLE events ADD COLUMN note TEXT;
+
+-- Interruptions are counted per session and, like extensions, never become
+-- a session's final_event
+DROP VIEW IF EXISTS sessions;
+
+CREATE VIEW sessions AS
+SELECT
+    e.session_id,
+    (SELECT timer_type FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS timer_type,
+    (SELECT task FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS task,
+    MIN(e.at)                          AS started_at,
+    MAX(e.at)                          AS ended_at,
+    MAX(e.work_secs)                   AS work_secs,
+    COALESCE(SUM(e.extended_secs), 0)  AS extended_secs,
+    COUNT(CASE WHEN e.interruption_kind = 'Internal' THEN 1 END) AS internal_interruptions,
+    COUNT(CASE WHEN e.interruption_kind = 'External' THEN 1 END) AS external_interruptions,
+    (SELECT event_type FROM events
+     WHERE session_id = e.session_id AND event_type NOT IN ('Extended', 'Interrupted')
+     ORDER BY at DESC, id DESC LIMIT 1) AS final_event
+FROM events e
+GROUP BY e.session_id;
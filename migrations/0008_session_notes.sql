@@ -0,0 +1,7 @@
+CREATE TABLE IF NOT EXISTS session_notes (
+    session_id TEXT    PRIMARY KEY,
+    note       TEXT,
+    -- Focus quality, 1 (scattered) to 5 (deep focus)
+    rating     INTEGER CHECK (rating BETWEEN 1 AND 5),
+    updated_at TEXT    NOT NULL
+);
@@ -0,0 +1,8 @@
+-- A single daily focus goal: either a number of completed work sessions or
+-- seconds of completed focus time
+CREATE TABLE IF NOT EXISTS goals (
+    id         INTEGER PRIMARY KEY CHECK (id = 1),
+    kind       TEXT    NOT NULL CHECK (kind IN ('sessions', 'focus_secs')),
+    target     INTEGER NOT NULL CHECK (target > 0),
+    updated_at TEXT    NOT NULL
+);
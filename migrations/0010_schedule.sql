@@ -0,0 +1,37 @@
+-- Weekly work-hours blocks; the server starts the timer when one begins and
+-- stops auto-continue when it ends
+CREATE TABLE IF NOT EXISTS schedule_blocks (
+    id         INTEGER PRIMARY KEY AUTOINCREMENT,
+    -- 0 = Monday ... 6 = Sunday
+    weekday    INTEGER NOT NULL CHECK (weekday BETWEEN 0 AND 6),
+    -- Local time, HH:MM
+    start_time TEXT    NOT NULL,
+    end_time   TEXT    NOT NULL CHECK (end_time > start_time),
+    created_at TEXT    NOT NULL
+);
+
+-- Schedule events are recorded against a session but are not part of its
+-- lifecycle: they never become its final_event or move its end time
+DROP VIEW IF EXISTS sessions;
+
+CREATE VIEW sessions AS
+SELECT
+    e.session_id,
+    (SELECT timer_type FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS timer_type,
+    (SELECT task FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS task,
+    MIN(e.at)                          AS started_at,
+    MAX(CASE WHEN e.event_type <> 'ScheduleStopped' THEN e.at END) AS ended_at,
+    MAX(e.work_secs)                   AS work_secs,
+    COALESCE(SUM(e.extended_secs), 0)  AS extended_secs,
+    COUNT(CASE WHEN e.interruption_kind = 'Internal' THEN 1 END) AS internal_interruptions,
+    COUNT(CASE WHEN e.interruption_kind = 'External' THEN 1 END) AS external_interruptions,
+    (SELECT event_type FROM events
+     WHERE session_id = e.session_id
+       AND event_type NOT IN ('Extended', 'Interrupted', 'ScheduleStarted', 'ScheduleStopped')
+     ORDER BY at DESC, id DESC LIMIT 1) AS final_event
+FROM events e
+GROUP BY e.session_id;
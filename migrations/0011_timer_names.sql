@@ -0,0 +1,30 @@
+-- Each server can run several named timers; events record which one they
+-- came from. Everything before this migration belonged to the only timer.
+ALTER TABLE events ADD COLUMN timer_name TEXT NOT NULL DEFAULT 'default';
+
+CREATE INDEX IF NOT EXISTS idx_events_timer_name ON events(timer_name);
+
+DROP VIEW IF EXISTS sessions;
+
+CREATE VIEW sessions AS
+SELECT
+    e.session_id,
+    MIN(e.timer_name)                  AS timer_name,
+    (SELECT timer_type FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS timer_type,
+    (SELECT task FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS task,
+    MIN(e.at)                          AS started_at,
+    MAX(CASE WHEN e.event_type <> 'ScheduleStopped' THEN e.at END) AS ended_at,
+    MAX(e.work_secs)                   AS work_secs,
+    COALESCE(SUM(e.extended_secs), 0)  AS extended_secs,
+    COUNT(CASE WHEN e.interruption_kind = 'Internal' THEN 1 END) AS internal_interruptions,
+    COUNT(CASE WHEN e.interruption_kind = 'External' THEN 1 END) AS external_interruptions,
+    (SELECT event_type FROM events
+     WHERE session_id = e.session_id
+       AND event_type NOT IN ('Extended', 'Interrupted', 'ScheduleStarted', 'ScheduleStopped')
+     ORDER BY at DESC, id DESC LIMIT 1) AS final_event
+FROM events e
+GROUP BY e.session_id;
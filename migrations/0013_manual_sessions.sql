@@ -0,0 +1,42 @@
+-- Where an event came from: 'timer' when the timer recorded it live, 'manual'
+-- when a session was logged or corrected afterwards through the API
+ALTER TABLE events ADD COLUMN source TEXT NOT NULL DEFAULT 'timer'
+    CHECK (source IN ('timer', 'manual'));
+
+DROP VIEW IF EXISTS sessions;
+
+CREATE VIEW sessions AS
+WITH lifecycle AS (
+    SELECT session_id, event_type, at,
+           LEAD(at) OVER (PARTITION BY session_id ORDER BY at, id) AS next_at
+    FROM events
+    WHERE event_type IN ('Started', 'Paused', 'Resumed', 'Completed', 'Terminated')
+)
+SELECT
+    e.session_id,
+    MIN(e.timer_name)                  AS timer_name,
+    (SELECT timer_type FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS timer_type,
+    (SELECT task FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS task,
+    MIN(e.at)                          AS started_at,
+    MAX(CASE WHEN e.event_type <> 'ScheduleStopped' THEN e.at END) AS ended_at,
+    MAX(e.work_secs)                   AS work_secs,
+    COALESCE(SUM(e.extended_secs), 0)  AS extended_secs,
+    COUNT(CASE WHEN e.interruption_kind = 'Internal' THEN 1 END) AS internal_interruptions,
+    COUNT(CASE WHEN e.interruption_kind = 'External' THEN 1 END) AS external_interruptions,
+    (SELECT COALESCE(SUM(CAST(strftime('%s', next_at) AS INTEGER)
+                         - CAST(strftime('%s', at) AS INTEGER)), 0)
+     FROM lifecycle
+     WHERE session_id = e.session_id AND event_type = 'Paused' AND next_at IS NOT NULL
+    )                                  AS paused_secs,
+    MAX(e.termination_reason)          AS termination_reason,
+    MAX(e.source = 'manual')           AS manual,
+    (SELECT event_type FROM events
+     WHERE session_id = e.session_id
+       AND event_type NOT IN ('Extended', 'Interrupted', 'ScheduleStarted', 'ScheduleStopped')
+     ORDER BY at DESC, id DESC LIMIT 1) AS final_event
+FROM events e
+GROUP BY e.session_id;
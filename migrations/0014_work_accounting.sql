@@ -0,0 +1,54 @@
+-- Started events record the preset and the planned seconds; Completed and
+-- Terminated record the planned seconds (extensions included) and the time
+-- spent paused, next to work_secs which now only counts active time
+ALTER TABLE events ADD COLUMN planned_secs INTEGER;
+ALTER TABLE events ADD COLUMN paused_secs INTEGER;
+ALTER TABLE events ADD COLUMN preset TEXT;
+
+UPDATE events SET planned_secs = remaining_secs WHERE event_type = 'Started';
+
+-- paused_secs prefers what the timer recorded and falls back to the gaps after
+-- Paused events for sessions that are still open or predate this migration
+DROP VIEW IF EXISTS sessions;
+
+CREATE VIEW sessions AS
+WITH lifecycle AS (
+    SELECT session_id, event_type, at,
+           LEAD(at) OVER (PARTITION BY session_id ORDER BY at, id) AS next_at
+    FROM events
+    WHERE event_type IN ('Started', 'Paused', 'Resumed', 'Completed', 'Terminated')
+)
+SELECT
+    e.session_id,
+    MIN(e.timer_name)                  AS timer_name,
+    (SELECT timer_type FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS timer_type,
+    (SELECT task FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS task,
+    (SELECT preset FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS preset,
+    MIN(e.at)                          AS started_at,
+    MAX(CASE WHEN e.event_type <> 'ScheduleStopped' THEN e.at END) AS ended_at,
+    MAX(e.planned_secs)                AS planned_secs,
+    MAX(e.work_secs)                   AS work_secs,
+    COALESCE(SUM(e.extended_secs), 0)  AS extended_secs,
+    COUNT(CASE WHEN e.interruption_kind = 'Internal' THEN 1 END) AS internal_interruptions,
+    COUNT(CASE WHEN e.interruption_kind = 'External' THEN 1 END) AS external_interruptions,
+    COALESCE(
+        MAX(e.paused_secs),
+        (SELECT COALESCE(SUM(CAST(strftime('%s', next_at) AS INTEGER)
+                             - CAST(strftime('%s', at) AS INTEGER)), 0)
+         FROM lifecycle
+         WHERE session_id = e.session_id AND event_type = 'Paused' AND next_at IS NOT NULL)
+    )                                  AS paused_secs,
+    MAX(e.termination_reason)          AS termination_reason,
+    MAX(e.source = 'manual')           AS manual,
+    (SELECT event_type FROM events
+     WHERE session_id = e.session_id
+       AND event_type NOT IN ('Extended', 'Interrupted', 'ScheduleStarted', 'ScheduleStopped')
+     ORDER BY at DESC, id DESC LIMIT 1) AS final_event
+FROM events e
+GROUP BY e.session_id;
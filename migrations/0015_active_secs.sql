@@ -0,0 +1,61 @@
+-- work_secs held the running time of break sessions too; it becomes the neutral
+-- active_secs, and the view only reports work_secs for work and stopwatch sessions
+DROP VIEW IF EXISTS sessions;
+
+ALTER TABLE events RENAME COLUMN work_secs TO active_secs;
+
+-- Every event carries the mode of its session, not just Started
+UPDATE events SET timer_type = (
+    SELECT s.timer_type FROM events s
+    WHERE s.session_id = events.session_id AND s.event_type = 'Started'
+    LIMIT 1
+)
+WHERE timer_type IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_events_type_at ON events(event_type, at);
+
+-- Skipped events mark a break that was switched away from before it started;
+-- they belong to no session
+CREATE VIEW sessions AS
+WITH lifecycle AS (
+    SELECT session_id, event_type, at,
+           LEAD(at) OVER (PARTITION BY session_id ORDER BY at, id) AS next_at
+    FROM events
+    WHERE event_type IN ('Started', 'Paused', 'Resumed', 'Completed', 'Terminated')
+)
+SELECT
+    e.session_id,
+    MIN(e.timer_name)                  AS timer_name,
+    (SELECT timer_type FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS timer_type,
+    (SELECT task FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS task,
+    (SELECT preset FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS preset,
+    MIN(e.at)                          AS started_at,
+    MAX(CASE WHEN e.event_type <> 'ScheduleStopped' THEN e.at END) AS ended_at,
+    MAX(e.planned_secs)                AS planned_secs,
+    MAX(e.active_secs)                 AS active_secs,
+    MAX(CASE WHEN e.timer_type IN ('Work', 'Stopwatch') THEN e.active_secs END) AS work_secs,
+    COALESCE(SUM(e.extended_secs), 0)  AS extended_secs,
+    COUNT(CASE WHEN e.interruption_kind = 'Internal' THEN 1 END) AS internal_interruptions,
+    COUNT(CASE WHEN e.interruption_kind = 'External' THEN 1 END) AS external_interruptions,
+    COALESCE(
+        MAX(e.paused_secs),
+        (SELECT COALESCE(SUM(CAST(strftime('%s', next_at) AS INTEGER)
+                             - CAST(strftime('%s', at) AS INTEGER)), 0)
+         FROM lifecycle
+         WHERE session_id = e.session_id AND event_type = 'Paused' AND next_at IS NOT NULL)
+    )                                  AS paused_secs,
+    MAX(e.termination_reason)          AS termination_reason,
+    MAX(e.source = 'manual')           AS manual,
+    (SELECT event_type FROM events
+     WHERE session_id = e.session_id
+       AND event_type NOT IN ('Extended', 'Interrupted', 'ScheduleStarted', 'ScheduleStopped')
+     ORDER BY at DESC, id DESC LIMIT 1) AS final_event
+FROM events e
+WHERE e.event_type <> 'Skipped'
+GROUP BY e.session_id;
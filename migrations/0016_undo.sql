@@ -0,0 +1,57 @@
+-- An Undone event cancels the Terminated or Skipped events logged before it in
+-- the same session. live_events leaves those out; everything reading session
+-- outcomes goes through it.
+CREATE VIEW live_events AS
+SELECT * FROM events e
+WHERE NOT (
+    e.event_type IN ('Terminated', 'Skipped')
+    AND EXISTS (SELECT 1 FROM events u
+                WHERE u.session_id = e.session_id AND u.event_type = 'Undone' AND u.id > e.id)
+);
+
+DROP VIEW IF EXISTS sessions;
+
+CREATE VIEW sessions AS
+WITH lifecycle AS (
+    SELECT session_id, event_type, at,
+           LEAD(at) OVER (PARTITION BY session_id ORDER BY at, id) AS next_at
+    FROM live_events
+    WHERE event_type IN ('Started', 'Paused', 'Resumed', 'Completed', 'Terminated')
+)
+SELECT
+    e.session_id,
+    MIN(e.timer_name)                  AS timer_name,
+    (SELECT timer_type FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS timer_type,
+    (SELECT task FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS task,
+    (SELECT preset FROM events
+     WHERE session_id = e.session_id AND event_type = 'Started'
+     LIMIT 1)                          AS preset,
+    MIN(e.at)                          AS started_at,
+    MAX(CASE WHEN e.event_type <> 'ScheduleStopped' THEN e.at END) AS ended_at,
+    MAX(e.planned_secs)                AS planned_secs,
+    MAX(e.active_secs)                 AS active_secs,
+    MAX(CASE WHEN e.timer_type IN ('Work', 'Stopwatch') THEN e.active_secs END) AS work_secs,
+    COALESCE(SUM(e.extended_secs), 0)  AS extended_secs,
+    COUNT(CASE WHEN e.interruption_kind = 'Internal' THEN 1 END) AS internal_interruptions,
+    COUNT(CASE WHEN e.interruption_kind = 'External' THEN 1 END) AS external_interruptions,
+    COALESCE(
+        MAX(e.paused_secs),
+        (SELECT COALESCE(SUM(CAST(strftime('%s', next_at) AS INTEGER)
+                             - CAST(strftime('%s', at) AS INTEGER)), 0)
+         FROM lifecycle
+         WHERE session_id = e.session_id AND event_type = 'Paused' AND next_at IS NOT NULL)
+    )                                  AS paused_secs,
+    MAX(e.termination_reason)          AS termination_reason,
+    MAX(e.source = 'manual')           AS manual,
+    (SELECT event_type FROM live_events
+     WHERE session_id = e.session_id
+       AND event_type NOT IN ('Extended', 'Interrupted', 'ScheduleStarted', 'ScheduleStopped',
+                              'Undone')
+     ORDER BY at DESC, id DESC LIMIT 1) AS final_event
+FROM live_events e
+WHERE e.event_type NOT IN ('Skipped', 'Undone')
+GROUP BY e.session_id;
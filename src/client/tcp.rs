@@ -1,26 +1,68 @@
 // Copyright (c) 2025 Yu-Wen Chen
 // Licensed under the MIT License (see LICENSE file)
 
-use anyhow::{Ok, Result};
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use crate::protocol::messages::{
+    CAPABILITIES, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION, Push, PushEnvelope, RequestEnvelope,
+    ResponseEnvelope,
+};
 use crate::protocol::{Request, Response};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+use crate::timer::TimerStatus;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
 use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
 use tokio::time::timeout;
 
-#[derive(Debug, Default)]
+/// Requests waiting for their response, by id
+type Pending = Arc<Mutex<BTreeMap<u64, oneshot::Sender<Response>>>>;
+/// Where pushes go once the client subscribed
+type Pushes = Arc<Mutex<Option<mpsc::Sender<Push>>>>;
+/// Pushes a subscriber can fall behind by before it is dropped
+const PUSH_BUFFER: usize = 64;
+
+/// Write half of the connection, over TCP or a Unix socket
+type Writer = Arc<tokio::sync::Mutex<BufWriter<Box<dyn AsyncWrite + Send + Unpin>>>>;
+
+/// Client for the TCP server. Requests are tagged with ids, so clones of a
+/// connected client can have several requests in flight at once; a reader
+/// task hands each response to the request it answers.
+#[derive(Default, Clone)]
 pub struct PomoClient {
-    reader: Option<BufReader<tokio::net::tcp::OwnedReadHalf>>,
-    writer: Option<BufWriter<tokio::net::tcp::OwnedWriteHalf>>,
+    writer: Option<Writer>,
+    pending: Pending,
+    pushes: Pushes,
+    next_id: Arc<AtomicU64>,
+    /// Named timer to send requests to; `None` for the server's default timer
+    timer: Option<String>,
+    /// Capabilities the server announced in the handshake
+    capabilities: Vec<String>,
+}
+
+impl fmt::Debug for PomoClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PomoClient")
+            .field("connected", &self.writer.is_some())
+            .field("timer", &self.timer)
+            .field("capabilities", &self.capabilities)
+            .finish_non_exhaustive()
+    }
 }
 
 impl PomoClient {
     pub fn new() -> Self {
-        Self {
-            reader: None,
-            writer: None,
-        }
+        Self::default()
+    }
+
+    /// Sends every request to the named timer
+    pub fn with_timer(mut self, timer: Option<String>) -> Self {
+        self.timer = timer;
+        self
     }
 
     pub async fn connect(&mut self, addr: &str) -> Result<()> {
@@ -28,63 +70,224 @@ impl PomoClient {
 
         // Note: Split the stream into read/write halves
         let (read_half, write_half) = stream.into_split();
+        self.attach(read_half, write_half).await
+    }
+
+    /// Connects to a server's Unix socket
+    #[cfg(unix)]
+    pub async fn connect_unix(&mut self, path: &std::path::Path) -> Result<()> {
+        let stream = tokio::net::UnixStream::connect(path).await?;
+        let (read_half, write_half) = stream.into_split();
+        self.attach(read_half, write_half).await
+    }
 
-        self.reader = Some(BufReader::new(read_half));
-        self.writer = Some(BufWriter::new(write_half));
+    /// Connects over the Unix socket if a server listens on it, and over TCP
+    /// otherwise
+    pub async fn connect_local(
+        &mut self,
+        socket: Option<&std::path::Path>,
+        addr: &str,
+    ) -> Result<()> {
+        #[cfg(unix)]
+        if let Some(path) = socket {
+            match self.connect_unix(path).await {
+                Ok(()) => return Ok(()),
+                Err(e) => log::debug!("No server on {}, trying TCP: {e}", path.display()),
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = socket;
+        self.connect(addr).await
+    }
 
+    async fn attach<R, W>(&mut self, read_half: R, write_half: W) -> Result<()>
+    where
+        R: AsyncRead + Unpin + Send + 'static,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
+        let write_half: Box<dyn AsyncWrite + Send + Unpin> = Box::new(write_half);
+        self.writer = Some(Arc::new(tokio::sync::Mutex::new(BufWriter::new(
+            write_half,
+        ))));
+        tokio::spawn(read_responses(
+            BufReader::new(read_half),
+            self.pending.clone(),
+            self.pushes.clone(),
+        ));
+
+        self.handshake().await?;
+        if self.timer.is_some() {
+            // Reading a named timer doesn't create it, so make sure it exists
+            self.send_request(Request::CreateTimer).await?;
+        }
         Ok(())
     }
 
-    pub async fn send_request(&mut self, request: Request) -> Result<Response> {
-        let reader = self
-            .reader
-            .as_mut()
-            .ok_or_else(|| anyhow::anyhow!("Reader not connected"))?;
+    /// Exchanges protocol versions and capabilities with the server
+    async fn handshake(&mut self) -> Result<()> {
+        let hello = Request::Hello {
+            version: PROTOCOL_VERSION,
+            capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+        };
+        match self.exchange(&hello).await? {
+            Response::Hello {
+                version,
+                capabilities,
+            } if version >= MIN_PROTOCOL_VERSION => {
+                if version != PROTOCOL_VERSION {
+                    log::info!("Server speaks protocol {version}, this client {PROTOCOL_VERSION}");
+                }
+                self.capabilities = capabilities;
+                Ok(())
+            }
+            Response::Hello { version, .. } => Err(anyhow::anyhow!(
+                "Incompatible server: it speaks protocol {version}, \
+                 this client needs {MIN_PROTOCOL_VERSION} or later. Please upgrade the server."
+            )),
+            // Servers from before the handshake reject it as invalid JSON
+            Response::Error(e) if e.message.starts_with("Invalid JSON") => Err(anyhow::anyhow!(
+                "Incompatible server: it predates protocol versioning \
+                 (this client speaks {PROTOCOL_VERSION}). Please upgrade the server."
+            )),
+            Response::Error(e) => Err(anyhow::anyhow!("Incompatible server: {e}")),
+            other => Err(anyhow::anyhow!("Unexpected handshake response: {other:?}")),
+        }
+    }
+
+    /// Whether the server announced `capability` in the handshake
+    pub fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    /// Asks the server to push the timer's changes. Returns the current
+    /// status and a channel with everything that changes after it. The
+    /// channel closes if the connection drops or the receiver falls more than
+    /// `PUSH_BUFFER` pushes behind.
+    pub async fn subscribe(&self) -> Result<(TimerStatus, mpsc::Receiver<Push>)> {
+        let (tx, rx) = mpsc::channel(PUSH_BUFFER);
+        *self.pushes.lock().unwrap() = Some(tx);
+        match self.send_request(Request::Subscribe).await? {
+            Response::Status(status) => Ok((status, rx)),
+            Response::Error(e) => Err(anyhow::anyhow!(e)),
+            other => Err(anyhow::anyhow!(
+                "Unexpected response to Subscribe: {other:?}"
+            )),
+        }
+    }
+
+    pub async fn send_request(&self, request: Request) -> Result<Response> {
+        let request = request.for_timer(self.timer.as_deref());
+        self.exchange(&request).await
+    }
+
+    async fn exchange(&self, request: &Request) -> Result<Response> {
         let writer = self
             .writer
-            .as_mut()
+            .as_ref()
             .ok_or_else(|| anyhow::anyhow!("Writer not connected"))?;
 
-        // Serialize request to JSON
-        let request_json = serde_json::to_string(&request)?;
-
-        // Send JSON request with newline
-        writer.write_all(request_json.as_bytes()).await?;
-        writer.write_all(b"\n").await?;
-        writer.flush().await?;
+        // Register before sending so a fast response can't be missed
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, tx);
 
-        // Read JSON response
-        let mut response_line = String::new();
-        let bytes_read =
-            timeout(Duration::from_secs(5), reader.read_line(&mut response_line)).await??;
-
-        if bytes_read == 0 {
-            return Err(anyhow::anyhow!("Connection closed by server"));
+        let request_json = serde_json::to_string(&RequestEnvelope { id, request })?;
+        let sent = async {
+            let mut writer = writer.lock().await;
+            writer.write_all(request_json.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+            writer.flush().await
+        };
+        if let Err(e) = sent.await {
+            self.pending.lock().unwrap().remove(&id);
+            return Err(e.into());
         }
 
-        // Parse JSON response
-        let response: Response = serde_json::from_str(response_line.trim())?;
-
-        Ok(response)
+        match timeout(Duration::from_secs(5), rx).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) => Err(anyhow::anyhow!("Connection closed by server")),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&id);
+                Err(anyhow::anyhow!("Timed out waiting for the server"))
+            }
+        }
     }
 
     // Convenience functions
-    pub async fn set_task_name(&mut self, task_name: String) -> Result<()> {
+    pub async fn set_task_name(&self, task_name: String) -> Result<()> {
         self.send_request(Request::SetTask(task_name)).await?;
         Ok(())
     }
 
-    pub async fn change_task_name(&mut self, task_name: String) -> Result<()> {
+    pub async fn change_task_name(&self, task_name: String) -> Result<()> {
         self.send_request(Request::ChangeTask(task_name)).await?;
         Ok(())
     }
 
-    pub async fn reset(&mut self) -> Result<()> {
+    pub async fn reset(&self) -> Result<()> {
         self.send_request(Request::Reset).await?;
         Ok(())
     }
 
-    pub async fn get_status(&mut self) -> Result<Response> {
+    pub async fn get_status(&self) -> Result<Response> {
         self.send_request(Request::GetStatus).await
     }
 }
+
+/// Hands each response line to the request it answers, and pushes to the
+/// subscriber, until the connection closes. Then drops the remaining requests
+/// and the subscriber so they see an error.
+async fn read_responses<R: AsyncRead + Unpin>(
+    mut reader: BufReader<R>,
+    pending: Pending,
+    pushes: Pushes,
+) {
+    let mut line = String::new();
+    loop {
+        line.clear();
+        match reader.read_line(&mut line).await {
+            Ok(0) => break,
+            Ok(_) => {}
+            Err(e) => {
+                log::error!("Error reading from server: {e}");
+                break;
+            }
+        }
+        if let Ok(envelope) = serde_json::from_str::<PushEnvelope>(line.trim()) {
+            let mut pushes = pushes.lock().unwrap();
+            if let Some(sender) = pushes.as_ref()
+                && let Err(mpsc::error::TrySendError::Full(_)) = sender.try_send(envelope.push)
+            {
+                // Rather than losing changes silently, let the subscriber
+                // notice and fall back to asking for the status
+                log::warn!("Subscriber fell behind, dropping its subscription");
+                pushes.take();
+            }
+            continue;
+        }
+        let (id, response) = match serde_json::from_str::<ResponseEnvelope>(line.trim()) {
+            Ok(envelope) => (Some(envelope.id), envelope.response),
+            // A server that didn't understand the envelope answers without an id
+            Err(_) => match serde_json::from_str::<Response>(line.trim()) {
+                Ok(response) => (None, response),
+                Err(e) => {
+                    log::error!("Invalid response from server: {e}");
+                    continue;
+                }
+            },
+        };
+        let sender = {
+            let mut pending = pending.lock().unwrap();
+            match id {
+                Some(id) => pending.remove(&id),
+                // Responses come back in order, so it answers the oldest request
+                None => pending.pop_first().map(|(_, sender)| sender),
+            }
+        };
+        if let Some(sender) = sender {
+            let _ = sender.send(response);
+        }
+    }
+    pending.lock().unwrap().clear();
+    pushes.lock().unwrap().take();
+}
@@ -0,0 +1,95 @@
+// Copyright (c) 2025 Yu-Wen Chen
+// Licensed under the MIT License (see LICENSE file)
+
+use chrono::{DateTime, Local};
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Source of time for the timer and server. Production code uses
+/// `SystemClock`; tests and `--simulate` use `ManualClock` so time-dependent
+/// behavior can be fast-forwarded instead of waited for.
+pub trait Clock: Send + Sync + fmt::Debug {
+    /// Monotonic time, used for countdowns
+    fn now(&self) -> Instant;
+
+    /// Wall-clock time, used for event timestamps
+    fn local_now(&self) -> DateTime<Local>;
+
+    fn elapsed(&self, since: Instant) -> Duration {
+        self.now().saturating_duration_since(since)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn local_now(&self) -> DateTime<Local> {
+        Local::now()
+    }
+}
+
+/// A clock that only moves when `advance` is called
+#[derive(Debug)]
+pub struct ManualClock {
+    instant: Instant,
+    local: DateTime<Local>,
+    offset: Mutex<Duration>,
+}
+
+impl ManualClock {
+    /// Starts at the current system time
+    pub fn new() -> Self {
+        Self {
+            instant: Instant::now(),
+            local: Local::now(),
+            offset: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.offset.lock().unwrap() += by;
+    }
+
+    fn offset(&self) -> Duration {
+        *self.offset.lock().unwrap()
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.instant + self.offset()
+    }
+
+    fn local_now(&self) -> DateTime<Local> {
+        self.local + self.offset()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manual_clock_only_moves_on_advance() {
+        let clock = ManualClock::new();
+        let t0 = clock.now();
+        let wall = clock.local_now();
+        assert_eq!(clock.elapsed(t0), Duration::ZERO);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.elapsed(t0), Duration::from_secs(90));
+        assert_eq!((clock.local_now() - wall).num_seconds(), 90);
+    }
+}
@@ -1,157 +1,370 @@
-use anyhow::Result;
-use chrono::Utc;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, Utc};
 use serde::Serialize;
-use sqlx::SqlitePool;
+use sqlx::{SqliteExecutor, SqlitePool};
+use std::time::Duration;
 
-use crate::timer::LogEvent;
+use crate::timer::{LogEvent, TimerMode};
+use uuid::Uuid;
+
+/// `events.source` for events the timer recorded live
+const SOURCE_TIMER: &str = "timer";
+/// `events.source` for sessions logged or corrected through the API
+const SOURCE_MANUAL: &str = "manual";
+
+/// Timestamps are stored as UTC RFC 3339 with second precision
+fn fmt_at(at: DateTime<Local>) -> String {
+    at.with_timezone(&Utc)
+        .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+}
 
 #[derive(Debug, Serialize, sqlx::FromRow)]
 pub struct SessionRow {
     pub session_id: String,
+    pub timer_name: String,
     pub timer_type: Option<String>,
     pub task: Option<String>,
+    /// Preset the session was started with; `None` for sessions logged by hand
+    pub preset: Option<String>,
     pub started_at: String,
     pub ended_at: String,
+    /// Time the session had when it started, plus extensions
+    pub planned_secs: Option<i64>,
+    /// Seconds the session actually ran, pauses excluded
+    pub active_secs: Option<i64>,
+    /// `active_secs` of work and stopwatch sessions; `None` for breaks
     pub work_secs: Option<i64>,
+    pub extended_secs: i64,
+    pub internal_interruptions: i64,
+    pub external_interruptions: i64,
+    /// Seconds spent paused, up to the last resume or termination
+    pub paused_secs: i64,
+    /// Why the server terminated the session, e.g. "max_pause"
+    pub termination_reason: Option<String>,
+    /// Logged or corrected after the fact rather than recorded by the timer
+    pub manual: bool,
     pub final_event: Option<String>,
+    pub note: Option<String>,
+    /// Focus rating from 1 to 5
+    pub rating: Option<i64>,
+}
+
+/// A timer's most recent session, if it never received a Terminated/Completed event
+#[derive(Debug, sqlx::FromRow)]
+pub struct OpenSession {
+    pub session_id: String,
+    pub timer_name: String,
+    pub timer_type: Option<String>,
+    pub task: Option<String>,
+    pub started_at: String,
+    /// Remaining seconds when the session started
+    pub planned_secs: Option<i64>,
+    pub extended_secs: i64,
+    pub paused_secs: i64,
+    pub final_event: String,
+    pub last_at: String,
+    pub last_remaining_secs: Option<i64>,
+}
+
+/// Records an event from the named timer
+pub async fn insert_event(pool: &SqlitePool, timer: &str, event: &LogEvent) -> Result<()> {
+    insert_event_from(pool, timer, SOURCE_TIMER, event).await
+}
+
+/// Columns of `events` that only some event types fill in
+#[derive(Default)]
+struct EventColumns<'a> {
+    timer_type: Option<String>,
+    remaining_secs: Option<i64>,
+    active_secs: Option<i64>,
+    extended_secs: Option<i64>,
+    interruption_kind: Option<String>,
+    note: Option<&'a str>,
+    termination_reason: Option<&'static str>,
+    planned_secs: Option<i64>,
+    paused_secs: Option<i64>,
+    preset: Option<&'a str>,
 }
 
-pub async fn insert_event(pool: &SqlitePool, event: &LogEvent) -> Result<()> {
-    let (session_id, event_type, timer_type, task, at, remaining_secs, work_secs) = match event {
-        LogEvent::Idle => return Ok(()),
+async fn insert_event_from(
+    executor: impl SqliteExecutor<'_>,
+    timer: &str,
+    source: &str,
+    event: &LogEvent,
+) -> Result<()> {
+    let (Some(id), Some(task), Some(at)) = (event.id(), event.task(), event.at()) else {
+        // Idle carries nothing to record
+        return Ok(());
+    };
+    let columns = match event {
+        LogEvent::Idle => unreachable!("handled above"),
         LogEvent::Started {
-            id,
             timer_type,
-            task,
-            at,
-            remaining,
-        } => (
-            id.to_string(),
-            "Started",
-            Some(timer_type.to_string()),
-            task.as_str(),
-            at.with_timezone(&Utc)
-                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
-            Some(*remaining as i64),
-            None::<i64>,
-        ),
-        LogEvent::Paused {
-            id,
-            task,
-            at,
-            remaining,
-        } => (
-            id.to_string(),
-            "Paused",
-            None,
-            task.as_str(),
-            at.with_timezone(&Utc)
-                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
-            Some(*remaining as i64),
-            None,
-        ),
-        LogEvent::Resumed {
-            id,
-            task,
-            at,
             remaining,
-        } => (
-            id.to_string(),
-            "Resumed",
-            None,
-            task.as_str(),
-            at.with_timezone(&Utc)
-                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
-            Some(*remaining as i64),
-            None,
-        ),
+            preset,
+            ..
+        } => EventColumns {
+            timer_type: Some(timer_type.to_string()),
+            remaining_secs: Some(*remaining as i64),
+            planned_secs: Some(*remaining as i64),
+            preset: preset.as_deref(),
+            ..Default::default()
+        },
+        LogEvent::Paused { remaining, .. }
+        | LogEvent::Resumed { remaining, .. }
+        | LogEvent::ScheduleStarted { remaining, .. }
+        | LogEvent::ScheduleStopped { remaining, .. }
+        | LogEvent::Undone { remaining, .. } => EventColumns {
+            remaining_secs: Some(*remaining as i64),
+            ..Default::default()
+        },
         LogEvent::Terminated {
-            id,
-            task,
-            at,
             remaining,
-            work_secs,
-        } => (
-            id.to_string(),
-            "Terminated",
-            None,
-            task.as_str(),
-            at.with_timezone(&Utc)
-                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
-            Some(*remaining as i64),
-            Some(*work_secs as i64),
-        ),
+            active_secs,
+            planned_secs,
+            paused_secs,
+            reason,
+            ..
+        } => EventColumns {
+            remaining_secs: Some(*remaining as i64),
+            active_secs: Some(*active_secs as i64),
+            termination_reason: reason.map(|r| r.as_str()),
+            planned_secs: Some(*planned_secs as i64),
+            paused_secs: Some(*paused_secs as i64),
+            ..Default::default()
+        },
         LogEvent::Completed {
-            id,
-            task,
-            at,
-            work_secs,
-        } => (
-            id.to_string(),
-            "Completed",
-            None,
-            task.as_str(),
-            at.with_timezone(&Utc)
-                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
-            None::<i64>,
-            Some(*work_secs as i64),
-        ),
+            active_secs,
+            planned_secs,
+            paused_secs,
+            ..
+        } => EventColumns {
+            active_secs: Some(*active_secs as i64),
+            planned_secs: Some(*planned_secs as i64),
+            paused_secs: Some(*paused_secs as i64),
+            ..Default::default()
+        },
+        LogEvent::Extended {
+            added, remaining, ..
+        } => EventColumns {
+            remaining_secs: Some(*remaining as i64),
+            extended_secs: Some(*added as i64),
+            ..Default::default()
+        },
+        LogEvent::Interrupted {
+            kind,
+            note,
+            remaining,
+            ..
+        } => EventColumns {
+            remaining_secs: Some(*remaining as i64),
+            interruption_kind: Some(kind.to_string()),
+            note: note.as_deref(),
+            ..Default::default()
+        },
+        LogEvent::Skipped { timer_type, .. } => EventColumns {
+            timer_type: Some(timer_type.to_string()),
+            ..Default::default()
+        },
     };
 
     sqlx::query(
         "INSERT INTO events \
-         (session_id, event_type, timer_type, task, at, remaining_secs, work_secs) \
-         VALUES (?, ?, ?, ?, ?, ?, ?)",
+         (session_id, event_type, timer_type, task, at, remaining_secs, active_secs, \
+         extended_secs, interruption_kind, note, timer_name, termination_reason, source, \
+         planned_secs, paused_secs, preset) \
+         VALUES (?1, ?2, COALESCE(?3, (SELECT timer_type FROM events \
+         WHERE session_id = ?1 AND event_type IN ('Started', 'Skipped') LIMIT 1)), \
+         ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
     )
-    .bind(session_id)
-    .bind(event_type)
-    .bind(timer_type)
+    .bind(id.to_string())
+    .bind(event.name())
+    .bind(columns.timer_type)
     .bind(task)
-    .bind(at)
-    .bind(remaining_secs)
-    .bind(work_secs)
-    .execute(pool)
+    .bind(fmt_at(at))
+    .bind(columns.remaining_secs)
+    .bind(columns.active_secs)
+    .bind(columns.extended_secs)
+    .bind(columns.interruption_kind)
+    .bind(columns.note)
+    .bind(timer)
+    .bind(columns.termination_reason)
+    .bind(source)
+    .bind(columns.planned_secs)
+    .bind(columns.paused_secs)
+    .bind(columns.preset)
+    .execute(executor)
     .await?;
 
     Ok(())
 }
 
-pub async fn get_sessions(pool: &SqlitePool, limit: u32) -> Result<Vec<SessionRow>> {
-    let limit = limit.clamp(1, 100) as i64;
-    let rows = sqlx::query_as::<_, SessionRow>(
-        "SELECT session_id, timer_type, task, started_at, ended_at, work_secs, final_event \
-         FROM sessions \
-         ORDER BY started_at DESC \
-         LIMIT ?",
+/// Logs a finished session after the fact as a Started and a Completed event.
+/// Returns the new session's id.
+pub async fn insert_manual_session(
+    pool: &SqlitePool,
+    timer: &str,
+    mode: TimerMode,
+    task: &str,
+    started_at: DateTime<Local>,
+    duration: Duration,
+) -> Result<Uuid> {
+    let ended_at = chrono::Duration::from_std(duration)
+        .ok()
+        .and_then(|length| started_at.checked_add_signed(length))
+        .context("Session ends out of range")?;
+    let id = Uuid::new_v4();
+    let secs = duration.as_secs();
+    let mut tx = pool.begin().await?;
+    let started = LogEvent::Started {
+        id,
+        timer_type: mode,
+        task: task.to_string(),
+        at: started_at,
+        remaining: secs,
+        preset: None,
+    };
+    insert_event_from(&mut *tx, timer, SOURCE_MANUAL, &started).await?;
+    let completed = LogEvent::Completed {
+        id,
+        task: task.to_string(),
+        at: ended_at,
+        active_secs: secs,
+        planned_secs: secs,
+        paused_secs: 0,
+    };
+    insert_event_from(&mut *tx, timer, SOURCE_MANUAL, &completed).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+/// Moves a finished session's start and end and renames its task. Events in
+/// between are kept inside the new range, the work time is recomputed from it
+/// minus the paused time, and the changed events are marked as manual.
+pub async fn update_session(
+    pool: &SqlitePool,
+    session_id: &str,
+    started_at: DateTime<Local>,
+    ended_at: DateTime<Local>,
+    task: &str,
+) -> Result<()> {
+    let (start, end) = (fmt_at(started_at), fmt_at(ended_at));
+    let mut tx = pool.begin().await?;
+    sqlx::query(
+        "UPDATE events SET at = MIN(MAX(at, ?1), ?2), task = ?3, source = ?4 \
+         WHERE session_id = ?5",
+    )
+    .bind(&start)
+    .bind(&end)
+    .bind(task)
+    .bind(SOURCE_MANUAL)
+    .bind(session_id)
+    .execute(&mut *tx)
+    .await?;
+    sqlx::query(
+        "UPDATE events SET at = CASE event_type WHEN 'Started' THEN ?1 ELSE ?2 END \
+         WHERE session_id = ?3 AND event_type IN ('Started', 'Completed', 'Terminated')",
     )
+    .bind(&start)
+    .bind(&end)
+    .bind(session_id)
+    .execute(&mut *tx)
+    .await?;
+    // Drop the recorded pause so the view recomputes it from the moved events
+    sqlx::query("UPDATE events SET paused_secs = NULL WHERE session_id = ?")
+        .bind(session_id)
+        .execute(&mut *tx)
+        .await?;
+    let (paused_secs,): (i64,) =
+        sqlx::query_as("SELECT paused_secs FROM sessions WHERE session_id = ?")
+            .bind(session_id)
+            .fetch_one(&mut *tx)
+            .await?;
+    let active_secs = ((ended_at - started_at).num_seconds() - paused_secs).max(0);
+    sqlx::query(
+        "UPDATE events SET active_secs = ?, paused_secs = ? \
+         WHERE session_id = ? AND event_type IN ('Completed', 'Terminated')",
+    )
+    .bind(active_secs)
+    .bind(paused_secs)
+    .bind(session_id)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+const SESSION_SELECT: &str = "SELECT s.session_id, s.timer_name, s.timer_type, s.task, \
+     s.preset, s.started_at, s.ended_at, s.planned_secs, s.active_secs, s.work_secs, \
+     s.extended_secs, s.internal_interruptions, s.external_interruptions, s.paused_secs, s.termination_reason, s.manual, s.final_event, \
+     n.note, n.rating \
+     FROM sessions s \
+     LEFT JOIN session_notes n ON n.session_id = s.session_id";
+
+/// Most recent sessions first, optionally only those of one timer
+pub async fn get_sessions(
+    pool: &SqlitePool,
+    limit: u32,
+    timer: Option<&str>,
+) -> Result<Vec<SessionRow>> {
+    let limit = limit.clamp(1, 100) as i64;
+    let rows = sqlx::query_as::<_, SessionRow>(&format!(
+        "{SESSION_SELECT} \
+         WHERE ?1 IS NULL OR s.timer_name = ?1 \
+         ORDER BY s.started_at DESC \
+         LIMIT ?2"
+    ))
+    .bind(timer)
     .bind(limit)
     .fetch_all(pool)
     .await?;
     Ok(rows)
 }
 
+pub async fn get_session(pool: &SqlitePool, session_id: &str) -> Result<Option<SessionRow>> {
+    let row = sqlx::query_as::<_, SessionRow>(&format!("{SESSION_SELECT} WHERE s.session_id = ?"))
+        .bind(session_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row)
+}
+
+/// The open session of each timer, for recovery after a restart
+pub async fn get_open_sessions(pool: &SqlitePool) -> Result<Vec<OpenSession>> {
+    let rows = sqlx::query_as::<_, OpenSession>(
+        "SELECT s.session_id, s.timer_name, s.timer_type, s.task, s.started_at, \
+         (SELECT remaining_secs FROM events \
+          WHERE session_id = s.session_id AND event_type = 'Started' \
+          LIMIT 1) AS planned_secs, \
+         s.extended_secs, s.paused_secs, s.final_event, e.at AS last_at, \
+         e.remaining_secs AS last_remaining_secs \
+         FROM sessions s \
+         JOIN events e ON e.id = (SELECT id FROM events WHERE session_id = s.session_id \
+                                  ORDER BY at DESC, id DESC LIMIT 1) \
+         WHERE s.started_at = (SELECT MAX(started_at) FROM sessions \
+                               WHERE timer_name = s.timer_name) \
+           AND s.final_event IN ('Started', 'Resumed', 'Paused') \
+         ORDER BY s.timer_name",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::timer::{LogEvent, TimerMode};
+    use crate::db::{insert_completed_session, test_pool};
+    use crate::timer::{DEFAULT_TIMER, InterruptionKind, LogEvent, TerminationReason, TimerMode};
     use chrono::Local;
-    use sqlx::pool::PoolOptions;
     use uuid::Uuid;
 
-    async fn test_pool() -> SqlitePool {
-        let pool = PoolOptions::<sqlx::Sqlite>::new()
-            .max_connections(1)
-            .connect("sqlite::memory:")
-            .await
-            .unwrap();
-        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
-        pool
-    }
-
     #[tokio::test]
     async fn test_idle_is_skipped() {
         let pool = test_pool().await;
-        insert_event(&pool, &LogEvent::Idle).await.unwrap();
+        insert_event(&pool, DEFAULT_TIMER, &LogEvent::Idle)
+            .await
+            .unwrap();
         let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM events")
             .fetch_one(&pool)
             .await
@@ -165,19 +378,21 @@ mod tests {
         let id = Uuid::new_v4();
         insert_event(
             &pool,
+            DEFAULT_TIMER,
             &LogEvent::Started {
                 id,
                 timer_type: TimerMode::Work,
                 task: "test task".into(),
                 at: Local::now(),
                 remaining: 1500,
+                preset: None,
             },
         )
         .await
         .unwrap();
 
         let row: (String, Option<String>, String, Option<i64>, Option<i64>) = sqlx::query_as(
-            "SELECT event_type, timer_type, task, remaining_secs, work_secs \
+            "SELECT event_type, timer_type, task, remaining_secs, active_secs \
                  FROM events WHERE session_id = ?",
         )
         .bind(id.to_string())
@@ -198,18 +413,21 @@ mod tests {
         let id = Uuid::new_v4();
         insert_event(
             &pool,
+            DEFAULT_TIMER,
             &LogEvent::Completed {
                 id,
                 task: "done".into(),
                 at: Local::now(),
-                work_secs: 1500,
+                active_secs: 1500,
+                planned_secs: 1500,
+                paused_secs: 0,
             },
         )
         .await
         .unwrap();
 
         let row: (String, Option<i64>, Option<i64>) = sqlx::query_as(
-            "SELECT event_type, remaining_secs, work_secs \
+            "SELECT event_type, remaining_secs, active_secs \
                  FROM events WHERE session_id = ?",
         )
         .bind(id.to_string())
@@ -222,6 +440,52 @@ mod tests {
         assert_eq!(row.2, Some(1500_i64));
     }
 
+    #[tokio::test]
+    async fn test_break_session_has_active_not_work_secs() {
+        let pool = test_pool().await;
+        let id = Uuid::new_v4();
+        let events = [
+            LogEvent::Started {
+                id,
+                timer_type: TimerMode::Break,
+                task: "t".into(),
+                at: Local::now(),
+                remaining: 300,
+                preset: None,
+            },
+            LogEvent::Paused {
+                id,
+                task: "t".into(),
+                at: Local::now(),
+                remaining: 200,
+            },
+            LogEvent::Completed {
+                id,
+                task: "t".into(),
+                at: Local::now(),
+                active_secs: 300,
+                planned_secs: 300,
+                paused_secs: 0,
+            },
+        ];
+        for e in &events {
+            insert_event(&pool, DEFAULT_TIMER, e).await.unwrap();
+        }
+
+        let modes: Vec<(Option<String>,)> =
+            sqlx::query_as("SELECT timer_type FROM events WHERE session_id = ?")
+                .bind(id.to_string())
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(modes.len(), 3);
+        assert!(modes.iter().all(|m| m.0.as_deref() == Some("Break")));
+
+        let session = get_session(&pool, &id.to_string()).await.unwrap().unwrap();
+        assert_eq!(session.active_secs, Some(300));
+        assert_eq!(session.work_secs, None);
+    }
+
     #[tokio::test]
     async fn test_insert_all_variants() {
         let pool = test_pool().await;
@@ -235,6 +499,7 @@ mod tests {
                 task: "t".into(),
                 at: now,
                 remaining: 1500,
+                preset: None,
             },
             LogEvent::Paused {
                 id,
@@ -253,12 +518,15 @@ mod tests {
                 task: "t".into(),
                 at: now,
                 remaining: 100,
-                work_secs: 1400,
+                active_secs: 1400,
+                reason: None,
+                planned_secs: 1400,
+                paused_secs: 0,
             },
         ];
 
         for e in &events {
-            insert_event(&pool, e).await.unwrap();
+            insert_event(&pool, DEFAULT_TIMER, e).await.unwrap();
         }
 
         let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM events")
@@ -271,34 +539,9 @@ mod tests {
     #[tokio::test]
     async fn test_get_sessions_full() {
         let pool = test_pool().await;
-        let id = Uuid::new_v4();
-        let now = Local::now();
-
-        insert_event(
-            &pool,
-            &LogEvent::Started {
-                id,
-                timer_type: TimerMode::Work,
-                task: "my task".into(),
-                at: now,
-                remaining: 1500,
-            },
-        )
-        .await
-        .unwrap();
-        insert_event(
-            &pool,
-            &LogEvent::Completed {
-                id,
-                task: "my task".into(),
-                at: now,
-                work_secs: 1500,
-            },
-        )
-        .await
-        .unwrap();
+        let id = insert_completed_session(&pool, TimerMode::Work, "my task", 1500).await;
 
-        let sessions = get_sessions(&pool, 20).await.unwrap();
+        let sessions = get_sessions(&pool, 20, None).await.unwrap();
         assert_eq!(sessions.len(), 1);
         assert_eq!(sessions[0].session_id, id.to_string());
         assert_eq!(sessions[0].timer_type.as_deref(), Some("Work"));
@@ -315,18 +558,20 @@ mod tests {
             let id = Uuid::new_v4();
             insert_event(
                 &pool,
+                DEFAULT_TIMER,
                 &LogEvent::Started {
                     id,
                     timer_type: TimerMode::Work,
                     task: "t".into(),
                     at: now,
                     remaining: 1500,
+                    preset: None,
                 },
             )
             .await
             .unwrap();
         }
-        let sessions = get_sessions(&pool, 3).await.unwrap();
+        let sessions = get_sessions(&pool, 3, None).await.unwrap();
         assert_eq!(sessions.len(), 3);
     }
 
@@ -336,17 +581,19 @@ mod tests {
         let id = Uuid::new_v4();
         insert_event(
             &pool,
+            DEFAULT_TIMER,
             &LogEvent::Started {
                 id,
                 timer_type: TimerMode::Work,
                 task: "t".into(),
                 at: Local::now(),
                 remaining: 1500,
+                preset: None,
             },
         )
         .await
         .unwrap();
-        let sessions = get_sessions(&pool, 0).await.unwrap();
+        let sessions = get_sessions(&pool, 0, None).await.unwrap();
         assert_eq!(sessions.len(), 1);
     }
 
@@ -358,18 +605,533 @@ mod tests {
             let id = Uuid::new_v4();
             insert_event(
                 &pool,
+                DEFAULT_TIMER,
                 &LogEvent::Started {
                     id,
                     timer_type: TimerMode::Work,
                     task: "t".into(),
                     at: now,
                     remaining: 1500,
+                    preset: None,
                 },
             )
             .await
             .unwrap();
         }
-        let sessions = get_sessions(&pool, 200).await.unwrap();
+        let sessions = get_sessions(&pool, 200, None).await.unwrap();
         assert_eq!(sessions.len(), 100);
     }
+
+    #[tokio::test]
+    async fn test_get_open_sessions() {
+        let pool = test_pool().await;
+        assert!(get_open_sessions(&pool).await.unwrap().is_empty());
+
+        let id = Uuid::new_v4();
+        let now = Local::now();
+        insert_event(
+            &pool,
+            DEFAULT_TIMER,
+            &LogEvent::Started {
+                id,
+                timer_type: TimerMode::Work,
+                task: "t".into(),
+                at: now,
+                remaining: 1500,
+                preset: None,
+            },
+        )
+        .await
+        .unwrap();
+        insert_event(
+            &pool,
+            DEFAULT_TIMER,
+            &LogEvent::Paused {
+                id,
+                task: "t".into(),
+                at: now,
+                remaining: 1200,
+            },
+        )
+        .await
+        .unwrap();
+
+        let open = get_open_sessions(&pool).await.unwrap().pop().unwrap();
+        assert_eq!(open.session_id, id.to_string());
+        assert_eq!(open.timer_type.as_deref(), Some("Work"));
+        assert_eq!(open.planned_secs, Some(1500));
+        assert_eq!(open.final_event, "Paused");
+        assert_eq!(open.last_remaining_secs, Some(1200));
+
+        insert_event(
+            &pool,
+            DEFAULT_TIMER,
+            &LogEvent::Terminated {
+                id,
+                task: "t".into(),
+                at: now,
+                remaining: 1200,
+                active_secs: 300,
+                reason: None,
+                planned_secs: 300,
+                paused_secs: 0,
+            },
+        )
+        .await
+        .unwrap();
+        assert!(get_open_sessions(&pool).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sessions_per_timer() {
+        let pool = test_pool().await;
+        let now = Local::now();
+        let started = |id, task: &str| LogEvent::Started {
+            id,
+            timer_type: TimerMode::Work,
+            task: task.into(),
+            at: now,
+            remaining: 1500,
+            preset: None,
+        };
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        insert_event(&pool, DEFAULT_TIMER, &started(a, "default"))
+            .await
+            .unwrap();
+        insert_event(&pool, "alice", &started(b, "old"))
+            .await
+            .unwrap();
+        insert_event(
+            &pool,
+            "alice",
+            &LogEvent::Completed {
+                id: b,
+                task: "old".into(),
+                at: now,
+                active_secs: 1500,
+                planned_secs: 1500,
+                paused_secs: 0,
+            },
+        )
+        .await
+        .unwrap();
+        let later = LogEvent::Started {
+            id: c,
+            timer_type: TimerMode::Work,
+            task: "new".into(),
+            at: now + chrono::Duration::minutes(30),
+            remaining: 1500,
+            preset: None,
+        };
+        insert_event(&pool, "alice", &later).await.unwrap();
+
+        let open = get_open_sessions(&pool).await.unwrap();
+        let open: Vec<_> = open
+            .iter()
+            .map(|o| (o.timer_name.as_str(), o.session_id.clone()))
+            .collect();
+        assert_eq!(
+            open,
+            vec![("alice", c.to_string()), ("default", a.to_string())]
+        );
+
+        let alice = get_sessions(&pool, 20, Some("alice")).await.unwrap();
+        assert_eq!(alice.len(), 2);
+        assert!(alice.iter().all(|s| s.timer_name == "alice"));
+        assert_eq!(get_sessions(&pool, 20, None).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_extended_is_not_final_event() {
+        let pool = test_pool().await;
+        let id = Uuid::new_v4();
+        let now = Local::now();
+        let events = vec![
+            LogEvent::Started {
+                id,
+                timer_type: TimerMode::Work,
+                task: "t".into(),
+                at: now,
+                remaining: 1500,
+                preset: None,
+            },
+            LogEvent::Extended {
+                id,
+                task: "t".into(),
+                at: now,
+                added: 300,
+                remaining: 1700,
+            },
+            LogEvent::Extended {
+                id,
+                task: "t".into(),
+                at: now,
+                added: 60,
+                remaining: 1750,
+            },
+        ];
+        for e in &events {
+            insert_event(&pool, DEFAULT_TIMER, e).await.unwrap();
+        }
+
+        let sessions = get_sessions(&pool, 20, None).await.unwrap();
+        assert_eq!(sessions[0].extended_secs, 360);
+        assert_eq!(sessions[0].final_event.as_deref(), Some("Started"));
+
+        let open = get_open_sessions(&pool).await.unwrap().pop().unwrap();
+        assert_eq!(open.last_remaining_secs, Some(1750));
+    }
+
+    #[tokio::test]
+    async fn test_interruptions_are_counted() {
+        let pool = test_pool().await;
+        let id = Uuid::new_v4();
+        let now = Local::now();
+        let interrupted = |kind, note: Option<&str>| LogEvent::Interrupted {
+            id,
+            task: "t".into(),
+            at: now,
+            kind,
+            note: note.map(String::from),
+            remaining: 1200,
+        };
+        let events = vec![
+            LogEvent::Started {
+                id,
+                timer_type: TimerMode::Work,
+                task: "t".into(),
+                at: now,
+                remaining: 1500,
+                preset: None,
+            },
+            interrupted(InterruptionKind::Internal, None),
+            interrupted(InterruptionKind::External, Some("call")),
+            interrupted(InterruptionKind::External, None),
+        ];
+        for e in &events {
+            insert_event(&pool, DEFAULT_TIMER, e).await.unwrap();
+        }
+
+        let sessions = get_sessions(&pool, 20, None).await.unwrap();
+        assert_eq!(sessions[0].internal_interruptions, 1);
+        assert_eq!(sessions[0].external_interruptions, 2);
+        assert_eq!(sessions[0].final_event.as_deref(), Some("Started"));
+
+        let note: (Option<String>,) =
+            sqlx::query_as("SELECT note FROM events WHERE interruption_kind = 'External' LIMIT 1")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(note.0.as_deref(), Some("call"));
+    }
+
+    #[tokio::test]
+    async fn test_schedule_events_do_not_end_session() {
+        let pool = test_pool().await;
+        let id = Uuid::new_v4();
+        let start = Local::now();
+        let done = start + chrono::Duration::minutes(25);
+        let events = vec![
+            LogEvent::Started {
+                id,
+                timer_type: TimerMode::Work,
+                task: "t".into(),
+                at: start,
+                remaining: 1500,
+                preset: None,
+            },
+            LogEvent::ScheduleStarted {
+                id,
+                task: "t".into(),
+                at: start,
+                remaining: 1500,
+            },
+            LogEvent::Completed {
+                id,
+                task: "t".into(),
+                at: done,
+                active_secs: 1500,
+                planned_secs: 1500,
+                paused_secs: 0,
+            },
+            LogEvent::ScheduleStopped {
+                id,
+                task: "t".into(),
+                at: done + chrono::Duration::minutes(10),
+                remaining: 0,
+            },
+        ];
+        for e in &events {
+            insert_event(&pool, DEFAULT_TIMER, e).await.unwrap();
+        }
+
+        let sessions = get_sessions(&pool, 20, None).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].final_event.as_deref(), Some("Completed"));
+        assert_eq!(
+            sessions[0].ended_at,
+            done.with_timezone(&chrono::Utc)
+                .to_rfc3339_opts(chrono::SecondsFormat::Secs, true)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_paused_secs_and_termination_reason() {
+        let pool = test_pool().await;
+        let id = Uuid::new_v4();
+        let start = Local::now();
+        let at = |mins| start + chrono::Duration::minutes(mins);
+        let events = vec![
+            LogEvent::Started {
+                id,
+                timer_type: TimerMode::Work,
+                task: "t".into(),
+                at: at(0),
+                remaining: 1500,
+                preset: None,
+            },
+            LogEvent::Paused {
+                id,
+                task: "t".into(),
+                at: at(5),
+                remaining: 1200,
+            },
+            // Extending doesn't end the pause
+            LogEvent::Extended {
+                id,
+                task: "t".into(),
+                at: at(6),
+                added: 300,
+                remaining: 1500,
+            },
+            LogEvent::Resumed {
+                id,
+                task: "t".into(),
+                at: at(8),
+                remaining: 1500,
+            },
+            LogEvent::Paused {
+                id,
+                task: "t".into(),
+                at: at(10),
+                remaining: 1380,
+            },
+            LogEvent::Terminated {
+                id,
+                task: "t".into(),
+                at: at(70),
+                remaining: 1380,
+                active_secs: 420,
+                reason: Some(TerminationReason::MaxPause),
+                planned_secs: 1800,
+                paused_secs: (3 + 60) * 60,
+            },
+        ];
+        for e in &events {
+            insert_event(&pool, DEFAULT_TIMER, e).await.unwrap();
+        }
+
+        let sessions = get_sessions(&pool, 20, None).await.unwrap();
+        assert_eq!(sessions[0].paused_secs, (3 + 60) * 60);
+        assert_eq!(sessions[0].termination_reason.as_deref(), Some("max_pause"));
+        assert_eq!(sessions[0].final_event.as_deref(), Some("Terminated"));
+    }
+
+    #[tokio::test]
+    async fn test_planned_paused_and_preset_columns() {
+        let pool = test_pool().await;
+        let id = Uuid::new_v4();
+        let start = Local::now();
+        let at = |mins| start + chrono::Duration::minutes(mins);
+        let events = vec![
+            LogEvent::Started {
+                id,
+                timer_type: TimerMode::Work,
+                task: "t".into(),
+                at: at(0),
+                remaining: 1500,
+                preset: Some("25/5".into()),
+            },
+            LogEvent::Paused {
+                id,
+                task: "t".into(),
+                at: at(5),
+                remaining: 1200,
+            },
+            LogEvent::Resumed {
+                id,
+                task: "t".into(),
+                at: at(7),
+                remaining: 1200,
+            },
+        ];
+        for e in &events {
+            insert_event(&pool, DEFAULT_TIMER, e).await.unwrap();
+        }
+
+        // While the session is open the pause comes from the event gaps
+        let sessions = get_sessions(&pool, 20, None).await.unwrap();
+        assert_eq!(sessions[0].preset.as_deref(), Some("25/5"));
+        assert_eq!(sessions[0].planned_secs, Some(1500));
+        assert_eq!(sessions[0].paused_secs, 120);
+
+        let completed = LogEvent::Completed {
+            id,
+            task: "t".into(),
+            at: at(27),
+            active_secs: 1500,
+            planned_secs: 1500,
+            paused_secs: 125,
+        };
+        insert_event(&pool, DEFAULT_TIMER, &completed)
+            .await
+            .unwrap();
+
+        // Once it ends, the pause the timer recorded wins over the event gaps
+        let sessions = get_sessions(&pool, 20, None).await.unwrap();
+        assert_eq!(sessions[0].work_secs, Some(1500));
+        assert_eq!(sessions[0].planned_secs, Some(1500));
+        assert_eq!(sessions[0].paused_secs, 125);
+    }
+
+    #[tokio::test]
+    async fn test_undone_termination_reopens_session() {
+        let pool = test_pool().await;
+        let id = Uuid::new_v4();
+        let start = Local::now() - chrono::Duration::hours(1);
+        let at = |mins| start + chrono::Duration::minutes(mins);
+        let events = [
+            LogEvent::Started {
+                id,
+                timer_type: TimerMode::Work,
+                task: "t".into(),
+                at: at(0),
+                remaining: 1500,
+                preset: None,
+            },
+            LogEvent::Terminated {
+                id,
+                task: "t".into(),
+                at: at(10),
+                remaining: 900,
+                active_secs: 600,
+                planned_secs: 1500,
+                paused_secs: 0,
+                reason: Some(TerminationReason::Reset),
+            },
+            LogEvent::Undone {
+                id,
+                task: "t".into(),
+                at: at(11),
+                remaining: 840,
+            },
+        ];
+        for e in &events {
+            insert_event(&pool, DEFAULT_TIMER, e).await.unwrap();
+        }
+
+        let open = get_open_sessions(&pool).await.unwrap();
+        assert_eq!(open.len(), 1);
+        assert_eq!(open[0].final_event, "Started");
+        assert_eq!(open[0].last_remaining_secs, Some(840));
+
+        let completed = LogEvent::Completed {
+            id,
+            task: "t".into(),
+            at: at(25),
+            active_secs: 1500,
+            planned_secs: 1500,
+            paused_secs: 0,
+        };
+        insert_event(&pool, DEFAULT_TIMER, &completed)
+            .await
+            .unwrap();
+        let session = get_session(&pool, &id.to_string()).await.unwrap().unwrap();
+        assert_eq!(session.final_event.as_deref(), Some("Completed"));
+        assert_eq!(session.termination_reason, None);
+        assert_eq!(session.work_secs, Some(1500));
+    }
+
+    #[tokio::test]
+    async fn test_manual_session_and_update() {
+        let pool = test_pool().await;
+        let start = Local::now() - chrono::Duration::hours(3);
+        let id = insert_manual_session(
+            &pool,
+            DEFAULT_TIMER,
+            TimerMode::Work,
+            "forgot to start",
+            start,
+            Duration::from_secs(1500),
+        )
+        .await
+        .unwrap();
+        let session = get_session(&pool, &id.to_string()).await.unwrap().unwrap();
+        assert!(session.manual);
+        assert_eq!(session.work_secs, Some(1500));
+        assert_eq!(session.final_event.as_deref(), Some("Completed"));
+        assert_eq!(
+            session.ended_at,
+            fmt_at(start + chrono::Duration::seconds(1500))
+        );
+
+        // A timed session with a ten minute pause, started five minutes late
+        let id = Uuid::new_v4();
+        let at = |mins| start + chrono::Duration::minutes(mins);
+        let events = vec![
+            LogEvent::Started {
+                id,
+                timer_type: TimerMode::Work,
+                task: "t".into(),
+                at: at(60),
+                remaining: 1500,
+                preset: None,
+            },
+            LogEvent::Paused {
+                id,
+                task: "t".into(),
+                at: at(70),
+                remaining: 900,
+            },
+            LogEvent::Resumed {
+                id,
+                task: "t".into(),
+                at: at(80),
+                remaining: 900,
+            },
+            LogEvent::Completed {
+                id,
+                task: "t".into(),
+                at: at(95),
+                active_secs: 1500,
+                planned_secs: 1500,
+                paused_secs: 0,
+            },
+        ];
+        for e in &events {
+            insert_event(&pool, DEFAULT_TIMER, e).await.unwrap();
+        }
+        let id = id.to_string();
+        assert!(!get_session(&pool, &id).await.unwrap().unwrap().manual);
+
+        update_session(&pool, &id, at(55), at(95), "renamed")
+            .await
+            .unwrap();
+        let session = get_session(&pool, &id).await.unwrap().unwrap();
+        assert!(session.manual);
+        assert_eq!(session.started_at, fmt_at(at(55)));
+        assert_eq!(session.task.as_deref(), Some("renamed"));
+        assert_eq!(session.paused_secs, 600);
+        assert_eq!(session.work_secs, Some(30 * 60));
+
+        // Cutting the end short pulls the pause inside the new range
+        update_session(&pool, &id, at(55), at(75), "renamed")
+            .await
+            .unwrap();
+        let session = get_session(&pool, &id).await.unwrap().unwrap();
+        assert_eq!(session.ended_at, fmt_at(at(75)));
+        assert_eq!(session.paused_secs, 5 * 60);
+        assert_eq!(session.work_secs, Some(15 * 60));
+        assert_eq!(session.final_event.as_deref(), Some("Completed"));
+    }
 }
@@ -0,0 +1,195 @@
+use anyhow::{Result, anyhow};
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+use crate::db::todos::get_daily_stats;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalKind {
+    /// Completed work sessions
+    Sessions,
+    /// Seconds of completed work
+    FocusSecs,
+}
+
+impl GoalKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            GoalKind::Sessions => "sessions",
+            GoalKind::FocusSecs => "focus_secs",
+        }
+    }
+}
+
+impl std::str::FromStr for GoalKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "sessions" => Ok(GoalKind::Sessions),
+            "focus_secs" => Ok(GoalKind::FocusSecs),
+            _ => Err(anyhow!("Unknown goal kind: {s}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DailyGoal {
+    pub kind: GoalKind,
+    pub target: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GoalProgress {
+    pub kind: GoalKind,
+    pub target: u64,
+    /// Sessions or seconds counted so far today
+    pub done: u64,
+    pub reached: bool,
+}
+
+impl GoalProgress {
+    /// e.g. "5/8 sessions" or "150/240 min"
+    pub fn summary(&self) -> String {
+        match self.kind {
+            GoalKind::Sessions => format!("{}/{} sessions", self.done, self.target),
+            GoalKind::FocusSecs => format!("{}/{} min", self.done / 60, self.target / 60),
+        }
+    }
+}
+
+pub async fn get_goal(pool: &SqlitePool) -> Result<Option<DailyGoal>> {
+    let row: Option<(String, i64)> = sqlx::query_as("SELECT kind, target FROM goals WHERE id = 1")
+        .fetch_optional(pool)
+        .await?;
+    row.map(|(kind, target)| {
+        Ok(DailyGoal {
+            kind: kind.parse()?,
+            target: target as u64,
+        })
+    })
+    .transpose()
+}
+
+pub async fn set_goal(pool: &SqlitePool, goal: &DailyGoal) -> Result<()> {
+    let now = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    sqlx::query(
+        "INSERT INTO goals (id, kind, target, updated_at) VALUES (1, ?, ?, ?) \
+         ON CONFLICT(id) DO UPDATE SET kind = excluded.kind, target = excluded.target, \
+         updated_at = excluded.updated_at",
+    )
+    .bind(goal.kind.as_str())
+    .bind(goal.target as i64)
+    .bind(&now)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Returns `false` if no goal was set.
+pub async fn clear_goal(pool: &SqlitePool) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM goals WHERE id = 1")
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Progress towards the goal on the local day `today`, counted like the daily stats
+pub async fn get_progress(pool: &SqlitePool, today: NaiveDate) -> Result<Option<GoalProgress>> {
+    let Some(goal) = get_goal(pool).await? else {
+        return Ok(None);
+    };
+    let stats = get_daily_stats(pool, today, 0).await?.into_iter().next();
+    let done = match (goal.kind, stats) {
+        (GoalKind::Sessions, Some(stats)) => stats.session_count as u64,
+        (GoalKind::FocusSecs, Some(stats)) => stats.total_work_secs as u64,
+        (_, None) => 0,
+    };
+    Ok(Some(GoalProgress {
+        kind: goal.kind,
+        target: goal.target,
+        done,
+        reached: done >= goal.target,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{insert_completed_session, test_pool};
+    use crate::timer::TimerMode;
+    use chrono::Local;
+
+    async fn complete_work(pool: &SqlitePool, secs: u64) {
+        insert_completed_session(pool, TimerMode::Work, "t", secs).await;
+    }
+
+    #[tokio::test]
+    async fn test_set_get_clear() {
+        let pool = test_pool().await;
+        assert!(get_goal(&pool).await.unwrap().is_none());
+        assert!(
+            get_progress(&pool, Local::now().date_naive())
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let goal = DailyGoal {
+            kind: GoalKind::Sessions,
+            target: 8,
+        };
+        set_goal(&pool, &goal).await.unwrap();
+        assert_eq!(get_goal(&pool).await.unwrap(), Some(goal));
+        assert!(clear_goal(&pool).await.unwrap());
+        assert!(!clear_goal(&pool).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_progress() {
+        let pool = test_pool().await;
+        set_goal(
+            &pool,
+            &DailyGoal {
+                kind: GoalKind::Sessions,
+                target: 2,
+            },
+        )
+        .await
+        .unwrap();
+        complete_work(&pool, 1500).await;
+        let progress = get_progress(&pool, Local::now().date_naive())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(progress.done, 1);
+        assert!(!progress.reached);
+
+        complete_work(&pool, 1500).await;
+        assert!(
+            get_progress(&pool, Local::now().date_naive())
+                .await
+                .unwrap()
+                .unwrap()
+                .reached
+        );
+
+        set_goal(
+            &pool,
+            &DailyGoal {
+                kind: GoalKind::FocusSecs,
+                target: 4 * 3600,
+            },
+        )
+        .await
+        .unwrap();
+        let progress = get_progress(&pool, Local::now().date_naive())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(progress.done, 3000);
+        assert!(!progress.reached);
+    }
+}
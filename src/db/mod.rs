@@ -7,6 +7,10 @@ use sqlx::{
 use std::{path::Path, time::Duration};
 
 pub mod events;
+pub mod goals;
+pub mod notes;
+pub mod presets;
+pub mod schedule;
 pub mod todos;
 
 pub async fn init(path: &Path) -> Result<SqlitePool> {
@@ -26,6 +30,56 @@ pub async fn init(path: &Path) -> Result<SqlitePool> {
     Ok(pool)
 }
 
+/// An in-memory database with the migrations applied
+#[cfg(test)]
+pub(crate) async fn test_pool() -> SqlitePool {
+    let pool = PoolOptions::<sqlx::Sqlite>::new()
+        .max_connections(1)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+    pool
+}
+
+/// Logs a `mode` session of `task` on the default timer that ran for `secs`
+/// and completed just now
+#[cfg(test)]
+pub(crate) async fn insert_completed_session(
+    pool: &SqlitePool,
+    mode: crate::timer::TimerMode,
+    task: &str,
+    secs: u64,
+) -> uuid::Uuid {
+    use crate::timer::{DEFAULT_TIMER, LogEvent};
+    use chrono::Local;
+
+    let id = uuid::Uuid::new_v4();
+    for event in [
+        LogEvent::Started {
+            id,
+            timer_type: mode,
+            task: task.into(),
+            at: Local::now(),
+            remaining: secs,
+            preset: None,
+        },
+        LogEvent::Completed {
+            id,
+            task: task.into(),
+            at: Local::now(),
+            active_secs: secs,
+            planned_secs: secs,
+            paused_secs: 0,
+        },
+    ] {
+        events::insert_event(pool, DEFAULT_TIMER, &event)
+            .await
+            .unwrap();
+    }
+    id
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
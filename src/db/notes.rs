@@ -0,0 +1,84 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct SessionNote {
+    pub session_id: String,
+    pub note: Option<String>,
+    /// Focus quality from 1 to 5
+    pub rating: Option<i64>,
+    pub updated_at: String,
+}
+
+/// Sets the note and rating of a session, replacing any previous ones
+pub async fn set_session_note(
+    pool: &SqlitePool,
+    session_id: &str,
+    note: Option<&str>,
+    rating: Option<u8>,
+) -> Result<()> {
+    let now = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    sqlx::query(
+        "INSERT INTO session_notes (session_id, note, rating, updated_at) \
+         VALUES (?, ?, ?, ?) \
+         ON CONFLICT(session_id) DO UPDATE SET note = excluded.note, \
+         rating = excluded.rating, updated_at = excluded.updated_at",
+    )
+    .bind(session_id)
+    .bind(note)
+    .bind(rating.map(i64::from))
+    .bind(&now)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_session_note(pool: &SqlitePool, session_id: &str) -> Result<Option<SessionNote>> {
+    let row = sqlx::query_as::<_, SessionNote>(
+        "SELECT session_id, note, rating, updated_at FROM session_notes WHERE session_id = ?",
+    )
+    .bind(session_id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+
+pub async fn session_exists(pool: &SqlitePool, session_id: &str) -> Result<bool> {
+    let row: Option<(i64,)> = sqlx::query_as("SELECT 1 FROM events WHERE session_id = ? LIMIT 1")
+        .bind(session_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    #[tokio::test]
+    async fn test_set_and_replace_note() {
+        let pool = test_pool().await;
+        assert!(get_session_note(&pool, "s1").await.unwrap().is_none());
+
+        set_session_note(&pool, "s1", Some("drafted intro"), Some(4))
+            .await
+            .unwrap();
+        let note = get_session_note(&pool, "s1").await.unwrap().unwrap();
+        assert_eq!(note.note.as_deref(), Some("drafted intro"));
+        assert_eq!(note.rating, Some(4));
+
+        set_session_note(&pool, "s1", None, Some(2)).await.unwrap();
+        let note = get_session_note(&pool, "s1").await.unwrap().unwrap();
+        assert!(note.note.is_none());
+        assert_eq!(note.rating, Some(2));
+    }
+
+    #[tokio::test]
+    async fn test_rating_out_of_range_is_rejected() {
+        let pool = test_pool().await;
+        assert!(set_session_note(&pool, "s1", None, Some(6)).await.is_err());
+    }
+}
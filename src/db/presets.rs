@@ -0,0 +1,129 @@
+use anyhow::Result;
+use chrono::Utc;
+use serde::Serialize;
+use sqlx::SqlitePool;
+use std::time::Duration;
+
+use crate::timer::Durations;
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct PresetRow {
+    pub name: String,
+    pub work_secs: i64,
+    pub break_secs: i64,
+    /// `None` falls back to the default multiple of `break_secs`
+    pub long_break_secs: Option<i64>,
+    pub created_at: String,
+}
+
+impl PresetRow {
+    pub fn durations(&self) -> Durations {
+        let durs = Durations::new(
+            Duration::from_secs(self.work_secs as u64),
+            Duration::from_secs(self.break_secs as u64),
+        );
+        match self.long_break_secs {
+            Some(secs) => durs.with_long_break(Duration::from_secs(secs as u64)),
+            None => durs,
+        }
+    }
+}
+
+/// Inserts a custom preset, or updates its durations if the name already exists.
+pub async fn upsert_preset(
+    pool: &SqlitePool,
+    name: &str,
+    work_secs: u64,
+    break_secs: u64,
+    long_break_secs: Option<u64>,
+) -> Result<()> {
+    let now = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    sqlx::query(
+        "INSERT INTO presets (name, work_secs, break_secs, long_break_secs, created_at) \
+         VALUES (?, ?, ?, ?, ?) \
+         ON CONFLICT(name) DO UPDATE SET work_secs = excluded.work_secs, \
+         break_secs = excluded.break_secs, long_break_secs = excluded.long_break_secs",
+    )
+    .bind(name)
+    .bind(i64::try_from(work_secs)?)
+    .bind(i64::try_from(break_secs)?)
+    .bind(long_break_secs.map(i64::try_from).transpose()?)
+    .bind(&now)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_preset(pool: &SqlitePool, name: &str) -> Result<Option<PresetRow>> {
+    let row = sqlx::query_as::<_, PresetRow>(
+        "SELECT name, work_secs, break_secs, long_break_secs, created_at \
+         FROM presets WHERE name = ?",
+    )
+    .bind(name)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row)
+}
+
+pub async fn get_all_presets(pool: &SqlitePool) -> Result<Vec<PresetRow>> {
+    let rows = sqlx::query_as::<_, PresetRow>(
+        "SELECT name, work_secs, break_secs, long_break_secs, created_at \
+         FROM presets ORDER BY created_at, name",
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+/// Returns `false` if no preset with that name existed.
+pub async fn delete_preset(pool: &SqlitePool, name: &str) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM presets WHERE name = ?")
+        .bind(name)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+
+    #[tokio::test]
+    async fn test_upsert_and_get() {
+        let pool = test_pool().await;
+        upsert_preset(&pool, "52/17", 52 * 60, 17 * 60, None)
+            .await
+            .unwrap();
+        let row = get_preset(&pool, "52/17").await.unwrap().unwrap();
+        assert_eq!(row.work_secs, 3120);
+        assert_eq!(row.break_secs, 1020);
+        assert!(row.long_break_secs.is_none());
+        assert_eq!(row.durations().long_brk.as_secs(), 3 * 1020);
+
+        upsert_preset(&pool, "52/17", 50 * 60, 10 * 60, Some(30 * 60))
+            .await
+            .unwrap();
+        let all = get_all_presets(&pool).await.unwrap();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].work_secs, 3000);
+        assert_eq!(all[0].long_break_secs, Some(1800));
+    }
+
+    #[tokio::test]
+    async fn test_get_missing() {
+        let pool = test_pool().await;
+        assert!(get_preset(&pool, "nope").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_delete() {
+        let pool = test_pool().await;
+        upsert_preset(&pool, "deep", 90 * 60, 20 * 60, None)
+            .await
+            .unwrap();
+        assert!(delete_preset(&pool, "deep").await.unwrap());
+        assert!(!delete_preset(&pool, "deep").await.unwrap());
+        assert!(get_all_presets(&pool).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,171 @@
+use anyhow::{Result, anyhow};
+use chrono::{Datelike, NaiveDateTime, NaiveTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use sqlx::SqlitePool;
+
+const TIME_FORMAT: &str = "%H:%M";
+
+/// A recurring block of work hours on one day of the week, in local time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScheduleBlock {
+    pub id: i64,
+    pub weekday: Weekday,
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl ScheduleBlock {
+    pub fn contains(&self, at: NaiveDateTime) -> bool {
+        at.weekday() == self.weekday && self.start <= at.time() && at.time() < self.end
+    }
+}
+
+/// The block `at` falls in, if any
+pub fn active_block(blocks: &[ScheduleBlock], at: NaiveDateTime) -> Option<&ScheduleBlock> {
+    blocks.iter().find(|b| b.contains(at))
+}
+
+fn check_times(start: NaiveTime, end: NaiveTime) -> Result<()> {
+    if start >= end {
+        return Err(anyhow!("Block must end after it starts"));
+    }
+    Ok(())
+}
+
+fn from_row((id, weekday, start, end): (i64, i64, String, String)) -> Result<ScheduleBlock> {
+    Ok(ScheduleBlock {
+        id,
+        weekday: Weekday::try_from(weekday as u8)?,
+        start: NaiveTime::parse_from_str(&start, TIME_FORMAT)?,
+        end: NaiveTime::parse_from_str(&end, TIME_FORMAT)?,
+    })
+}
+
+pub async fn get_blocks(pool: &SqlitePool) -> Result<Vec<ScheduleBlock>> {
+    let rows: Vec<(i64, i64, String, String)> = sqlx::query_as(
+        "SELECT id, weekday, start_time, end_time FROM schedule_blocks \
+         ORDER BY weekday, start_time",
+    )
+    .fetch_all(pool)
+    .await?;
+    rows.into_iter().map(from_row).collect()
+}
+
+pub async fn create_block(
+    pool: &SqlitePool,
+    weekday: Weekday,
+    start: NaiveTime,
+    end: NaiveTime,
+) -> Result<ScheduleBlock> {
+    check_times(start, end)?;
+    let now = Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let result = sqlx::query(
+        "INSERT INTO schedule_blocks (weekday, start_time, end_time, created_at) \
+         VALUES (?, ?, ?, ?)",
+    )
+    .bind(weekday.num_days_from_monday() as i64)
+    .bind(start.format(TIME_FORMAT).to_string())
+    .bind(end.format(TIME_FORMAT).to_string())
+    .bind(&now)
+    .execute(pool)
+    .await?;
+    Ok(ScheduleBlock {
+        id: result.last_insert_rowid(),
+        weekday,
+        start,
+        end,
+    })
+}
+
+/// Returns `false` if no block with that id existed.
+pub async fn update_block(
+    pool: &SqlitePool,
+    id: i64,
+    weekday: Weekday,
+    start: NaiveTime,
+    end: NaiveTime,
+) -> Result<bool> {
+    check_times(start, end)?;
+    let result = sqlx::query(
+        "UPDATE schedule_blocks SET weekday = ?, start_time = ?, end_time = ? WHERE id = ?",
+    )
+    .bind(weekday.num_days_from_monday() as i64)
+    .bind(start.format(TIME_FORMAT).to_string())
+    .bind(end.format(TIME_FORMAT).to_string())
+    .bind(id)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Returns `false` if no block with that id existed.
+pub async fn delete_block(pool: &SqlitePool, id: i64) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM schedule_blocks WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_pool;
+    use chrono::NaiveDate;
+
+    fn time(s: &str) -> NaiveTime {
+        NaiveTime::parse_from_str(s, TIME_FORMAT).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_crud() {
+        let pool = test_pool().await;
+        let block = create_block(&pool, Weekday::Tue, time("13:00"), time("17:30"))
+            .await
+            .unwrap();
+        create_block(&pool, Weekday::Mon, time("09:00"), time("12:00"))
+            .await
+            .unwrap();
+        assert!(
+            create_block(&pool, Weekday::Mon, time("12:00"), time("09:00"))
+                .await
+                .is_err()
+        );
+
+        let blocks = get_blocks(&pool).await.unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].weekday, Weekday::Mon);
+        assert_eq!(blocks[1], block);
+
+        assert!(
+            update_block(&pool, block.id, Weekday::Wed, time("14:00"), time("18:00"))
+                .await
+                .unwrap()
+        );
+        assert_eq!(get_blocks(&pool).await.unwrap()[1].weekday, Weekday::Wed);
+        assert!(delete_block(&pool, block.id).await.unwrap());
+        assert!(!delete_block(&pool, block.id).await.unwrap());
+    }
+
+    #[test]
+    fn test_active_block() {
+        let blocks = [ScheduleBlock {
+            id: 1,
+            weekday: Weekday::Mon,
+            start: time("09:00"),
+            end: time("12:00"),
+        }];
+        // 2026-03-30 is a Monday
+        let monday = NaiveDate::from_ymd_opt(2026, 3, 30).unwrap();
+        assert!(active_block(&blocks, monday.and_time(time("08:59"))).is_none());
+        assert_eq!(
+            active_block(&blocks, monday.and_time(time("09:00")))
+                .unwrap()
+                .id,
+            1
+        );
+        assert!(active_block(&blocks, monday.and_time(time("12:00"))).is_none());
+        let tuesday = monday.succ_opt().unwrap();
+        assert!(active_block(&blocks, tuesday.and_time(time("10:00"))).is_none());
+    }
+}
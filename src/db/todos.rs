@@ -1,5 +1,5 @@
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{NaiveDate, Utc};
 use serde::Serialize;
 use sqlx::SqlitePool;
 use uuid::Uuid;
@@ -153,19 +153,78 @@ pub struct DailyStats {
     pub date: String,
     pub session_count: i64,
     pub total_work_secs: i64,
+    /// Interruptions in all work sessions of the day, finished or not
+    pub interruption_count: i64,
 }
 
-pub async fn get_daily_stats(pool: &SqlitePool, days: u32) -> Result<Vec<DailyStats>> {
+/// Stats for `today` and the `days` before it. Days are local days, like
+/// `today`; the timestamps are stored in UTC.
+pub async fn get_daily_stats(
+    pool: &SqlitePool,
+    today: NaiveDate,
+    days: u32,
+) -> Result<Vec<DailyStats>> {
     let rows = sqlx::query_as::<_, DailyStats>(
-        "SELECT DATE(started_at) as date, \
-         COUNT(*) as session_count, \
-         COALESCE(SUM(work_secs), 0) as total_work_secs \
+        "SELECT DATE(started_at, 'localtime') as date, \
+         COUNT(CASE WHEN final_event = 'Completed' THEN 1 END) as session_count, \
+         COALESCE(SUM(CASE WHEN final_event = 'Completed' THEN work_secs END), 0) \
+         as total_work_secs, \
+         COALESCE(SUM(internal_interruptions + external_interruptions), 0) \
+         as interruption_count \
          FROM sessions \
-         WHERE timer_type = 'Work' AND final_event = 'Completed' \
-         AND started_at >= DATE('now', '-' || ? || ' days') \
-         GROUP BY DATE(started_at) \
+         WHERE timer_type IN ('Work', 'Stopwatch') \
+         AND DATE(started_at, 'localtime') >= DATE(?, '-' || ? || ' days') \
+         GROUP BY DATE(started_at, 'localtime') \
+         HAVING session_count > 0 OR interruption_count > 0 \
          ORDER BY date DESC",
     )
+    .bind(today.to_string())
+    .bind(days as i64)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows)
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+pub struct BreakStats {
+    pub date: String,
+    /// Breaks that ran to completion
+    pub taken: i64,
+    /// Breaks switched away from before they started
+    pub skipped: i64,
+    /// Breaks that were started but ended early
+    pub shortened: i64,
+    /// Seconds spent on taken and shortened breaks
+    pub total_break_secs: i64,
+}
+
+/// Break stats by local day, like `get_daily_stats`
+pub async fn get_break_stats(
+    pool: &SqlitePool,
+    today: NaiveDate,
+    days: u32,
+) -> Result<Vec<BreakStats>> {
+    let rows = sqlx::query_as::<_, BreakStats>(
+        "SELECT date, \
+         COALESCE(SUM(taken), 0) as taken, \
+         COALESCE(SUM(skipped), 0) as skipped, \
+         COALESCE(SUM(shortened), 0) as shortened, \
+         COALESCE(SUM(secs), 0) as total_break_secs \
+         FROM ( \
+             SELECT DATE(started_at, 'localtime') as date, final_event = 'Completed' as taken, 0 as skipped, \
+             final_event = 'Terminated' as shortened, active_secs as secs \
+             FROM sessions WHERE timer_type IN ('Break', 'LongBreak') \
+             UNION ALL \
+             SELECT DATE(at, 'localtime'), 0, 1, 0, 0 \
+             FROM live_events \
+             WHERE event_type = 'Skipped' AND timer_type IN ('Break', 'LongBreak') \
+         ) \
+         WHERE date >= DATE(?, '-' || ? || ' days') \
+         GROUP BY date \
+         HAVING taken > 0 OR skipped > 0 OR shortened > 0 \
+         ORDER BY date DESC",
+    )
+    .bind(today.to_string())
     .bind(days as i64)
     .fetch_all(pool)
     .await?;
@@ -191,17 +250,7 @@ pub async fn get_latest_session_id(pool: &SqlitePool) -> Result<Option<String>>
 #[cfg(test)]
 mod tests {
     use super::*;
-    use sqlx::pool::PoolOptions;
-
-    async fn test_pool() -> SqlitePool {
-        let pool = PoolOptions::<sqlx::Sqlite>::new()
-            .max_connections(1)
-            .connect("sqlite::memory:")
-            .await
-            .unwrap();
-        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
-        pool
-    }
+    use crate::db::test_pool;
 
     #[tokio::test]
     async fn test_insert_and_get_todos() {
@@ -274,6 +323,158 @@ mod tests {
         assert!(todos.is_empty());
     }
 
+    #[tokio::test]
+    async fn test_daily_stats_include_stopwatch() {
+        use crate::db::insert_completed_session;
+        use crate::timer::TimerMode;
+        use chrono::Local;
+
+        let pool = test_pool().await;
+        for (mode, secs) in [
+            (TimerMode::Work, 1500),
+            (TimerMode::Stopwatch, 3600),
+            (TimerMode::Break, 300),
+        ] {
+            insert_completed_session(&pool, mode, "t", secs).await;
+        }
+        let today = Local::now().date_naive();
+        let stats = get_daily_stats(&pool, today, 1).await.unwrap();
+        assert_eq!(stats.len(), 1);
+        // Grouped by the local day, not the UTC one
+        assert_eq!(stats[0].date, today.to_string());
+        assert_eq!(stats[0].session_count, 2);
+        assert_eq!(stats[0].total_work_secs, 5100);
+        assert_eq!(stats[0].interruption_count, 0);
+        let later = today + chrono::Days::new(3);
+        assert!(get_daily_stats(&pool, later, 1).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_break_stats() {
+        use crate::db::events::insert_event;
+        use crate::timer::{DEFAULT_TIMER, LogEvent, TimerMode};
+        use chrono::Local;
+
+        let pool = test_pool().await;
+        let (taken, shortened) = (Uuid::new_v4(), Uuid::new_v4());
+        let events = [
+            LogEvent::Started {
+                id: taken,
+                timer_type: TimerMode::Break,
+                task: "t".into(),
+                at: Local::now(),
+                remaining: 300,
+                preset: None,
+            },
+            LogEvent::Completed {
+                id: taken,
+                task: "t".into(),
+                at: Local::now(),
+                active_secs: 300,
+                planned_secs: 300,
+                paused_secs: 0,
+            },
+            LogEvent::Started {
+                id: shortened,
+                timer_type: TimerMode::LongBreak,
+                task: "t".into(),
+                at: Local::now(),
+                remaining: 900,
+                preset: None,
+            },
+            LogEvent::Terminated {
+                id: shortened,
+                task: "t".into(),
+                at: Local::now(),
+                remaining: 780,
+                active_secs: 120,
+                planned_secs: 900,
+                paused_secs: 0,
+                reason: None,
+            },
+            LogEvent::Skipped {
+                id: Uuid::new_v4(),
+                timer_type: TimerMode::Break,
+                task: "t".into(),
+                at: Local::now(),
+            },
+        ];
+        for e in &events {
+            insert_event(&pool, DEFAULT_TIMER, e).await.unwrap();
+        }
+
+        let stats = get_break_stats(&pool, Local::now().date_naive(), 1)
+            .await
+            .unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].taken, 1);
+        assert_eq!(stats[0].skipped, 1);
+        assert_eq!(stats[0].shortened, 1);
+        assert_eq!(stats[0].total_break_secs, 420);
+
+        // Breaks never count as work
+        let daily = get_daily_stats(&pool, Local::now().date_naive(), 1)
+            .await
+            .unwrap();
+        assert!(daily.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_daily_stats_count_interruptions() {
+        use crate::db::events::insert_event;
+        use crate::timer::{DEFAULT_TIMER, InterruptionKind, LogEvent, TimerMode};
+        use chrono::Local;
+
+        let pool = test_pool().await;
+        let id = Uuid::new_v4();
+        let events = [
+            LogEvent::Started {
+                id,
+                timer_type: TimerMode::Work,
+                task: "t".into(),
+                at: Local::now(),
+                remaining: 1500,
+                preset: None,
+            },
+            LogEvent::Interrupted {
+                id,
+                task: "t".into(),
+                at: Local::now(),
+                kind: InterruptionKind::Internal,
+                note: None,
+                remaining: 1200,
+            },
+            LogEvent::Interrupted {
+                id,
+                task: "t".into(),
+                at: Local::now(),
+                kind: InterruptionKind::External,
+                note: Some("door".into()),
+                remaining: 1000,
+            },
+            LogEvent::Terminated {
+                id,
+                task: "t".into(),
+                at: Local::now(),
+                remaining: 900,
+                active_secs: 600,
+                reason: None,
+                planned_secs: 600,
+                paused_secs: 0,
+            },
+        ];
+        for event in &events {
+            insert_event(&pool, DEFAULT_TIMER, event).await.unwrap();
+        }
+        let stats = get_daily_stats(&pool, Local::now().date_naive(), 1)
+            .await
+            .unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].session_count, 0);
+        assert_eq!(stats[0].total_work_secs, 0);
+        assert_eq!(stats[0].interruption_count, 2);
+    }
+
     #[tokio::test]
     async fn test_link_session() {
         let pool = test_pool().await;
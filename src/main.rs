@@ -2,8 +2,12 @@
 // Licensed under the MIT License (see LICENSE file)
 
 mod client;
+mod clock;
 mod db;
+// History and waybar writers, not wired up to the timer yet
+#[allow(dead_code)]
 mod logging;
+mod notify;
 mod protocol;
 mod server;
 mod timer;
@@ -12,13 +16,16 @@ mod tui;
 mod utils;
 
 use crate::client::tcp::PomoClient;
-use crate::server::core::PomoServer;
+use crate::clock::{Clock, ManualClock, SystemClock};
+use crate::notify::{NoticeKind, Notifications, NotifierKind};
+use crate::server::core::{PomoServer, RecoveryPolicy};
 use crate::server::http::HttpServer;
 use crate::server::tcp::TcpServer;
 use crate::tui::ServerApp;
 use anyhow::Result;
 use clap::Parser;
 use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 #[derive(Parser, Debug)]
@@ -33,17 +40,111 @@ struct Args {
 
     #[arg(long, default_value = "127.0.0.1:1881")]
     http_addr: String,
+
+    /// Unix socket for local clients, preferred over TCP
+    /// [default: $XDG_RUNTIME_DIR/pomo-tui.sock]
+    #[arg(long, value_name = "PATH")]
+    socket: Option<PathBuf>,
+
+    /// What to do with a session left open by a previous run
+    #[arg(long, value_enum, default_value_t = RecoveryPolicy::Resume)]
+    recovery: RecoveryPolicy,
+
+    /// How to deliver notifications
+    #[arg(long, value_enum, default_value_t = NotifierKind::Desktop)]
+    notifier: NotifierKind,
+
+    /// Shell command for `--notifier command`; gets POMO_EVENT, POMO_SUMMARY and POMO_BODY
+    #[arg(long, value_name = "CMD")]
+    notify_command: Option<String>,
+
+    /// Message for one kind of notice, e.g. 'completed={mode} done: {task}'
+    #[arg(long, value_name = "KIND=TEMPLATE", value_parser = notify::parse_template)]
+    notify_template: Vec<(NoticeKind, String)>,
+
+    /// Only notify about these kinds of notices
+    #[arg(long, value_enum, value_delimiter = ',')]
+    notify_events: Option<Vec<NoticeKind>>,
+
+    /// Terminate sessions that stay paused longer than this many minutes
+    #[arg(long, value_name = "MINUTES", value_parser = clap::value_parser!(u64).range(1..))]
+    max_pause: Option<u64>,
+
+    /// Named timer for the TUI to control; it is created when the TUI connects
+    #[arg(long, value_name = "NAME")]
+    timer: Option<String>,
+
+    /// Debug: run the timer this many times faster than real time
+    #[arg(long, value_name = "SPEED")]
+    simulate: Option<u32>,
+}
+
+/// A clock that runs `speed` times faster than real time, for trying out
+/// full sessions without waiting for them
+fn simulated_clock(speed: u32) -> Arc<dyn Clock> {
+    let clock = Arc::new(ManualClock::new());
+    let ticker = clock.clone();
+    tokio::spawn(async move {
+        let step = tokio::time::Duration::from_millis(100);
+        let mut interval = tokio::time::interval(step);
+        loop {
+            interval.tick().await;
+            ticker.advance(step * speed);
+        }
+    });
+    clock
+}
+
+/// The Unix socket local clients connect to, on platforms that have them
+fn socket_path(arg: Option<PathBuf>) -> Option<PathBuf> {
+    #[cfg(unix)]
+    return arg.or_else(server::unix::default_socket_path);
+    #[cfg(not(unix))]
+    {
+        let _ = arg;
+        None
+    }
+}
+
+/// Listens on the Unix socket. The TCP and HTTP servers keep running if it
+/// can't be opened.
+fn spawn_socket_server(
+    socket: Option<&Path>,
+    pomo_server: Arc<PomoServer>,
+) -> Option<tokio::task::JoinHandle<()>> {
+    #[cfg(unix)]
+    return socket.map(|path| {
+        let unix_server = server::unix::UnixServer::new(pomo_server);
+        let path = path.to_path_buf();
+        tokio::spawn(async move {
+            if let Err(e) = unix_server.start(&path).await {
+                log::error!("Unix socket {} unavailable: {e}", path.display());
+            }
+        })
+    });
+    #[cfg(not(unix))]
+    {
+        let _ = (socket, pomo_server);
+        None
+    }
 }
 
 async fn spawn_servers(
     tcp_addr: &str,
     http_addr: &str,
+    socket: Option<&Path>,
     pool: SqlitePool,
+    pomo_server: Arc<PomoServer>,
+    recovery: RecoveryPolicy,
 ) -> (
     tokio::task::JoinHandle<Result<()>>,
     tokio::task::JoinHandle<Result<()>>,
+    Option<tokio::task::JoinHandle<()>>,
 ) {
-    let pomo_server = Arc::new(PomoServer::new(pool.clone()));
+    if let Err(e) = pomo_server.recover(recovery).await {
+        log::error!("Failed to recover previous session: {e}");
+    }
+    let socket_server = spawn_socket_server(socket, pomo_server.clone());
     let tcp_server = TcpServer::new(pomo_server.clone());
     let http_server = HttpServer::new(pomo_server, pool);
 
@@ -52,12 +153,17 @@ async fn spawn_servers(
 
     let tcp_task = tokio::spawn(async move { tcp_server.start(&tcp_addr).await });
     let http_task = tokio::spawn(async move { http_server.start(&http_addr).await });
-    (tcp_task, http_task)
+    (tcp_task, http_task, socket_server)
 }
 
-async fn start_network_tui(tcp_addr: &str, pool: SqlitePool) -> Result<()> {
-    let mut client = PomoClient::new();
-    client.connect(tcp_addr).await?;
+async fn start_network_tui(
+    tcp_addr: &str,
+    socket: Option<&Path>,
+    pool: SqlitePool,
+    timer: Option<String>,
+) -> Result<()> {
+    let mut client = PomoClient::new().with_timer(timer);
+    client.connect_local(socket, tcp_addr).await?;
 
     let mut terminal = ratatui::init();
     let mut app = ServerApp::new(client, Some(pool));
@@ -70,15 +176,27 @@ async fn start_network_tui(tcp_addr: &str, pool: SqlitePool) -> Result<()> {
 async fn start_embedded_server_and_tui(
     tcp_addr: &str,
     http_addr: &str,
+    socket: Option<&Path>,
     pool: SqlitePool,
+    pomo_server: Arc<PomoServer>,
+    recovery: RecoveryPolicy,
+    timer: Option<String>,
 ) -> Result<()> {
-    let (tcp_server, http_server) = spawn_servers(tcp_addr, http_addr, pool.clone()).await;
+    let (tcp_server, http_server, socket_server) = spawn_servers(
+        tcp_addr,
+        http_addr,
+        socket,
+        pool.clone(),
+        pomo_server,
+        recovery,
+    )
+    .await;
 
     // Give servers time to start
     tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
 
-    let mut client = PomoClient::new();
-    client.connect(tcp_addr).await?;
+    let mut client = PomoClient::new().with_timer(timer);
+    client.connect_local(socket, tcp_addr).await?;
 
     let mut terminal = ratatui::init();
     let mut app = ServerApp::new(client, Some(pool.clone()));
@@ -87,19 +205,37 @@ async fn start_embedded_server_and_tui(
     ratatui::restore();
     tcp_server.abort();
     http_server.abort();
+    // Stopping the socket server removes the socket file
+    if let Some(socket_server) = socket_server {
+        socket_server.abort();
+        let _ = socket_server.await;
+    }
 
     Ok(())
 }
 
-async fn server_exists(tcp_addr: &str) -> bool {
-    match tokio::net::TcpStream::connect(tcp_addr).await {
-        Ok(_) => true,
-        Err(_) => false,
+async fn server_exists(tcp_addr: &str, socket: Option<&Path>) -> bool {
+    #[cfg(unix)]
+    if let Some(path) = socket
+        && tokio::net::UnixStream::connect(path).await.is_ok()
+    {
+        return true;
     }
+    #[cfg(not(unix))]
+    let _ = socket;
+    tokio::net::TcpStream::connect(tcp_addr).await.is_ok()
 }
 
-async fn start_server(tcp_addr: &str, http_addr: &str, pool: SqlitePool) -> Result<()> {
-    let (mut tcp_server, mut http_server) = spawn_servers(tcp_addr, http_addr, pool).await;
+async fn start_server(
+    tcp_addr: &str,
+    http_addr: &str,
+    socket: Option<&Path>,
+    pool: SqlitePool,
+    pomo_server: Arc<PomoServer>,
+    recovery: RecoveryPolicy,
+) -> Result<()> {
+    let (mut tcp_server, mut http_server, socket_server) =
+        spawn_servers(tcp_addr, http_addr, socket, pool, pomo_server, recovery).await;
 
     // Wait until one server exits or we receive a shutdown signal.
     tokio::select! {
@@ -121,6 +257,11 @@ async fn start_server(tcp_addr: &str, http_addr: &str, pool: SqlitePool) -> Resu
     // Optional: ensure abort is observed (avoid noisy "task was cancelled" later)
     let _ = tcp_server.await;
     let _ = http_server.await;
+    // Stopping the socket server removes the socket file
+    if let Some(socket_server) = socket_server {
+        socket_server.abort();
+        let _ = socket_server.await;
+    }
 
     Ok(())
 }
@@ -136,16 +277,66 @@ async fn main() -> Result<()> {
 
     let pool = db::init(&db_path).await?;
 
+    let clock: Arc<dyn Clock> = match args.simulate {
+        Some(speed) => {
+            println!("Simulating time at {speed}x speed");
+            simulated_clock(speed)
+        }
+        None => Arc::new(SystemClock),
+    };
+
+    let mut notifications = Notifications::from_kind(args.notifier, args.notify_command)?;
+    if let Some(kinds) = &args.notify_events {
+        notifications = notifications.only(kinds);
+    }
+    for (kind, template) in args.notify_template {
+        notifications = notifications.with_template(kind, template);
+    }
+    let notifications = Arc::new(notifications);
+    let max_pause = args
+        .max_pause
+        .map(|mins| std::time::Duration::from_secs(mins * 60));
+    let new_server = |pool: &SqlitePool| {
+        Arc::new(PomoServer::new(
+            pool.clone(),
+            clock,
+            notifications,
+            max_pause,
+        ))
+    };
+
+    let socket = socket_path(args.socket);
+    let socket = socket.as_deref();
+
     if args.server {
         println!("Starting Pomo server");
-        start_server(&args.tcp_addr, &args.http_addr, pool).await
+        let pomo_server = new_server(&pool);
+        start_server(
+            &args.tcp_addr,
+            &args.http_addr,
+            socket,
+            pool,
+            pomo_server,
+            args.recovery,
+        )
+        .await
     } else {
-        if server_exists(&args.tcp_addr).await {
+        if server_exists(&args.tcp_addr, socket).await {
             println!("Connecting to existing server ...");
-            start_network_tui(&args.tcp_addr, pool).await
+            start_network_tui(&args.tcp_addr, socket, pool, args.timer).await
         } else {
             println!("Starting embedded server and TUI");
-            start_embedded_server_and_tui(&args.tcp_addr, &args.http_addr, pool).await
+            let pomo_server = new_server(&pool);
+            start_embedded_server_and_tui(
+                &args.tcp_addr,
+                &args.http_addr,
+                socket,
+                pool,
+                pomo_server,
+                args.recovery,
+                args.timer,
+            )
+            .await
         }
     }
 }
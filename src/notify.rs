@@ -0,0 +1,292 @@
+// Copyright (c) 2025 Yu-Wen Chen
+// Licensed under the MIT License (see LICENSE file)
+
+use crate::timer::TimerMode;
+use anyhow::{Result, anyhow};
+use notify_rust::Notification;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::process::Command;
+
+/// Something the user should hear about. The timer queues these and the
+/// server hands them to the configured `Notifier`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, clap::ValueEnum)]
+pub enum NoticeKind {
+    /// A session ran out and was logged as Completed
+    Completed,
+    /// A session ran out in flow mode and is counting overtime
+    Overtime,
+    /// Today's focus goal was reached
+    GoalReached,
+    /// A session stayed paused past the maximum pause and was terminated
+    PauseExpired,
+}
+
+impl NoticeKind {
+    pub const ALL: [Self; 4] = [
+        Self::Completed,
+        Self::Overtime,
+        Self::GoalReached,
+        Self::PauseExpired,
+    ];
+
+    fn summary(self) -> &'static str {
+        match self {
+            Self::Completed => "Completed",
+            Self::Overtime => "Time's up",
+            Self::GoalReached => "Goal reached",
+            Self::PauseExpired => "Session ended",
+        }
+    }
+
+    fn default_template(self) -> &'static str {
+        match self {
+            Self::Completed => "{mode}: {task}",
+            Self::Overtime => "{mode}: {task} (counting overtime)",
+            Self::GoalReached => "Daily goal reached: {detail}",
+            Self::PauseExpired => "{mode}: {task} was paused too long and has been terminated",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notice {
+    pub kind: NoticeKind,
+    pub mode: TimerMode,
+    pub task: String,
+    /// Extra information for kinds that need it, e.g. the goal for `GoalReached`
+    pub detail: String,
+}
+
+/// A notification backend
+pub trait Notifier: Send + Sync {
+    fn send(&self, kind: NoticeKind, summary: &str, body: &str) -> Result<()>;
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum NotifierKind {
+    /// Desktop notification over D-Bus (or the platform equivalent)
+    #[default]
+    Desktop,
+    /// Ring the terminal bell
+    Bell,
+    /// Run `--notify-command` through `sh -c`
+    Command,
+    /// Don't notify
+    None,
+}
+
+pub struct DesktopNotifier;
+
+impl Notifier for DesktopNotifier {
+    fn send(&self, _kind: NoticeKind, summary: &str, body: &str) -> Result<()> {
+        Notification::new()
+            .summary(summary)
+            .body(body)
+            .icon("clock")
+            .show()?;
+        Ok(())
+    }
+}
+
+pub struct BellNotifier;
+
+impl Notifier for BellNotifier {
+    fn send(&self, _kind: NoticeKind, _summary: &str, _body: &str) -> Result<()> {
+        let mut out = std::io::stdout();
+        out.write_all(b"\x07")?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+/// Runs a shell command with the notification in `POMO_EVENT`, `POMO_SUMMARY`
+/// and `POMO_BODY`
+pub struct CommandNotifier {
+    command: String,
+}
+
+impl CommandNotifier {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            command: command.into(),
+        }
+    }
+}
+
+impl Notifier for CommandNotifier {
+    fn send(&self, kind: NoticeKind, summary: &str, body: &str) -> Result<()> {
+        let status = Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .env("POMO_EVENT", format!("{kind:?}"))
+            .env("POMO_SUMMARY", summary)
+            .env("POMO_BODY", body)
+            .status()?;
+        if !status.success() {
+            return Err(anyhow!("notify command exited with {status}"));
+        }
+        Ok(())
+    }
+}
+
+pub struct NoopNotifier;
+
+impl Notifier for NoopNotifier {
+    fn send(&self, _kind: NoticeKind, _summary: &str, _body: &str) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The configured backend plus which notices it gets and how they read
+pub struct Notifications {
+    backend: Box<dyn Notifier>,
+    templates: HashMap<NoticeKind, String>,
+    enabled: HashSet<NoticeKind>,
+}
+
+impl Notifications {
+    /// All notices enabled, with the default templates
+    pub fn new(backend: Box<dyn Notifier>) -> Self {
+        Self {
+            backend,
+            templates: HashMap::new(),
+            enabled: NoticeKind::ALL.into_iter().collect(),
+        }
+    }
+
+    pub fn none() -> Self {
+        Self::new(Box::new(NoopNotifier))
+    }
+
+    pub fn from_kind(kind: NotifierKind, command: Option<String>) -> Result<Self> {
+        let backend: Box<dyn Notifier> = match kind {
+            NotifierKind::Desktop => Box::new(DesktopNotifier),
+            NotifierKind::Bell => Box::new(BellNotifier),
+            NotifierKind::Command => {
+                let command =
+                    command.ok_or_else(|| anyhow!("--notifier command needs --notify-command"))?;
+                Box::new(CommandNotifier::new(command))
+            }
+            NotifierKind::None => return Ok(Self::none()),
+        };
+        Ok(Self::new(backend))
+    }
+
+    /// Only send these notices
+    pub fn only(mut self, kinds: &[NoticeKind]) -> Self {
+        self.enabled = kinds.iter().copied().collect();
+        self
+    }
+
+    /// Body template for one kind of notice. `{mode}`, `{task}`, `{event}` and
+    /// `{detail}` are replaced with the notice's values.
+    pub fn with_template(mut self, kind: NoticeKind, template: impl Into<String>) -> Self {
+        self.templates.insert(kind, template.into());
+        self
+    }
+
+    pub fn render(&self, notice: &Notice) -> String {
+        let template = self
+            .templates
+            .get(&notice.kind)
+            .map(String::as_str)
+            .unwrap_or(notice.kind.default_template());
+        template
+            .replace("{mode}", &notice.mode.to_string())
+            .replace("{task}", &notice.task)
+            .replace("{event}", &format!("{:?}", notice.kind))
+            .replace("{detail}", &notice.detail)
+    }
+
+    /// Sends the notice if its kind is enabled. Backends may block, so call
+    /// this off the async runtime.
+    pub fn dispatch(&self, notice: &Notice) {
+        if !self.enabled.contains(&notice.kind) {
+            return;
+        }
+        let body = self.render(notice);
+        if let Err(e) = self.backend.send(notice.kind, notice.kind.summary(), &body) {
+            log::warn!("Failed to send {:?} notification: {e}", notice.kind);
+        }
+    }
+}
+
+/// Parses a `KIND=TEMPLATE` argument, e.g. `completed={mode} done: {task}`
+pub fn parse_template(arg: &str) -> Result<(NoticeKind, String), String> {
+    let (kind, template) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected KIND=TEMPLATE, got '{arg}'"))?;
+    let kind = <NoticeKind as clap::ValueEnum>::from_str(kind.trim(), true)?;
+    Ok((kind, template.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Default)]
+    struct Recorder(Arc<Mutex<Vec<(NoticeKind, String, String)>>>);
+
+    impl Notifier for Recorder {
+        fn send(&self, kind: NoticeKind, summary: &str, body: &str) -> Result<()> {
+            self.0
+                .lock()
+                .unwrap()
+                .push((kind, summary.to_string(), body.to_string()));
+            Ok(())
+        }
+    }
+
+    fn notice(kind: NoticeKind) -> Notice {
+        Notice {
+            kind,
+            mode: TimerMode::Work,
+            task: "write report".into(),
+            detail: String::new(),
+        }
+    }
+
+    #[test]
+    fn default_and_custom_templates() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let n = Notifications::new(Box::new(Recorder(sent.clone())))
+            .with_template(NoticeKind::Overtime, "{event}! {task} ran over");
+        n.dispatch(&notice(NoticeKind::Completed));
+        n.dispatch(&notice(NoticeKind::Overtime));
+        let sent = sent.lock().unwrap();
+        assert_eq!(
+            sent[0],
+            (
+                NoticeKind::Completed,
+                "Completed".into(),
+                "Work: write report".into()
+            )
+        );
+        assert_eq!(sent[1].2, "Overtime! write report ran over");
+    }
+
+    #[test]
+    fn disabled_kinds_are_dropped() {
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let n = Notifications::new(Box::new(Recorder(sent.clone()))).only(&[NoticeKind::Overtime]);
+        n.dispatch(&notice(NoticeKind::Completed));
+        assert!(sent.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn parse_template_arg() {
+        assert_eq!(
+            parse_template("completed={mode} done").unwrap(),
+            (NoticeKind::Completed, "{mode} done".to_string())
+        );
+        assert!(parse_template("completed").is_err());
+        assert!(parse_template("nope=x").is_err());
+    }
+
+    #[test]
+    fn command_backend_requires_command() {
+        assert!(Notifications::from_kind(NotifierKind::Command, None).is_err());
+    }
+}
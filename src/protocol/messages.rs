@@ -1,8 +1,26 @@
 // Copyright (c) 2025 Yu-Wen Chen
 // Licensed under the MIT License (see LICENSE file)
 
-use crate::timer::{Preset, TimerStatus};
+use crate::timer::{InterruptionKind, LogEvent, Preset, TimerMode, TimerState, TimerStatus};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Version of the TCP protocol spoken by this build. Bump it when a request or
+/// response changes shape in a way older peers can't read.
+pub const PROTOCOL_VERSION: u32 = 2;
+/// Oldest peer version this build still talks to. Version 2 added typed
+/// errors and state transitions to the responses; version 1 peers get `Ok`
+/// and plain-string errors instead.
+pub const MIN_PROTOCOL_VERSION: u32 = 1;
+/// Optional features this build supports, exchanged in `Hello`
+pub const CAPABILITIES: &[&str] = &[
+    "named_timers",
+    "manual_sessions",
+    "undo",
+    "pending_preset",
+    "break_stats",
+    "subscribe",
+];
 
 #[derive(Clone, Serialize, Deserialize)]
 pub enum Request {
@@ -11,19 +29,241 @@ pub enum Request {
     Pause,
     Resume,
     Reset,
+    /// Go back to the state before the last reset, mode switch or preset change
+    Undo,
     SwitchMode,
+    /// Enter a mode directly, e.g. `Stopwatch` for untimed tracking
+    SetMode(TimerMode),
     SetTask(String),
     ChangeTask(String),
-    SetPreset(Preset),
+    /// Switch presets. With `next_session` the current session keeps going
+    /// and the preset is pending until it ends. Also accepts the older
+    /// `{"SetPreset": "<preset>"}` form, which switches right away.
+    #[serde(deserialize_with = "preset_change")]
+    SetPreset {
+        preset: Preset,
+        #[serde(default)]
+        next_session: bool,
+    },
+    SetCycleLength(u32),
+    SetAutoContinue(bool),
+    /// Grace period in seconds before an auto-continued session starts
+    SetGracePeriod(u64),
+    CancelGrace,
+    SkipGrace,
+    /// Add seconds to the current session
+    Extend(u64),
+    SetFlowMode(bool),
+    /// Attach a note and a 1-5 focus rating to a session, replacing earlier ones
+    SetSessionNote {
+        session_id: String,
+        #[serde(default)]
+        note: Option<String>,
+        #[serde(default)]
+        rating: Option<u8>,
+    },
+    /// Log an interruption of the current work session
+    Interrupt {
+        kind: InterruptionKind,
+        #[serde(default)]
+        note: Option<String>,
+    },
+    /// End a session that is counting overtime
+    Finish,
+    /// Names of the timers the server is running
+    ListTimers,
+    /// Create the timer if it doesn't exist yet, without changing it
+    CreateTimer,
+    /// Remove a named timer, terminating its session if one is running. The
+    /// default timer can't be deleted.
+    DeleteTimer,
+    /// Send `request` to the named timer instead of the default one. The
+    /// timer is created by the first request that changes it and succeeds.
+    ForTimer {
+        timer: String,
+        request: Box<Request>,
+    },
     Ping,
+    /// Ask the server to push status changes and logged events of the timer
+    /// to this connection. Answered with the current status.
+    Subscribe,
+    /// Handshake a client sends first on a new connection. Clients that skip
+    /// it are treated as speaking version 1, without capabilities.
+    Hello {
+        version: u32,
+        #[serde(default)]
+        capabilities: Vec<String>,
+    },
+}
+
+impl Request {
+    /// Addresses the request to `timer`, or leaves it for the default timer
+    pub fn for_timer(self, timer: Option<&str>) -> Self {
+        match timer {
+            Some(timer) => Self::ForTimer {
+                timer: timer.to_string(),
+                request: Box::new(self),
+            },
+            None => self,
+        }
+    }
+}
+
+/// `SetPreset` as a bare preset, from before `next_session` existed, or with
+/// its fields
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PresetChangeRepr {
+    Preset(Preset),
+    Change {
+        preset: Preset,
+        #[serde(default)]
+        next_session: bool,
+    },
+}
+
+fn preset_change<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<(Preset, bool), D::Error> {
+    Ok(match PresetChangeRepr::deserialize(deserializer)? {
+        PresetChangeRepr::Preset(preset) => (preset, false),
+        PresetChangeRepr::Change {
+            preset,
+            next_session,
+        } => (preset, next_session),
+    })
+}
+
+/// A request tagged with a client-chosen id, which the server echoes on the
+/// response. Lets a client keep several requests in flight on one connection.
+#[derive(Serialize, Deserialize)]
+pub struct RequestEnvelope<R = Request> {
+    pub id: u64,
+    pub request: R,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResponseEnvelope<R = Response> {
+    pub id: u64,
+    pub response: R,
+}
+
+/// Message the server sends unprompted to a subscribed connection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Push {
+    /// The timer's state changed other than by counting down
+    Status { timer: String, status: TimerStatus },
+    /// The timer logged an event; sent once it is stored
+    Event { timer: String, event: LogEvent },
+}
+
+impl Push {
+    pub fn timer(&self) -> &str {
+        match self {
+            Self::Status { timer, .. } | Self::Event { timer, .. } => timer,
+        }
+    }
+}
+
+/// Wraps a push on the wire so clients can tell it from a response
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PushEnvelope {
+    pub push: Push,
+}
+
+/// Machine-readable reason a request failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ErrorCode {
+    /// The request couldn't be parsed or makes no sense
+    InvalidRequest,
+    /// The client's protocol version is no longer supported
+    UnsupportedVersion,
+    /// A value in the request is out of range
+    InvalidValue,
+    /// The session, preset or timer doesn't exist
+    NotFound,
+    AlreadyRunning,
+    AlreadyPaused,
+    /// The timer is idle
+    NotRunning,
+    NotPaused,
+    NothingToUndo,
+    /// No session is waiting out a grace period
+    NoPendingSession,
+    /// The request needs a running or paused session
+    NoActiveSession,
+    /// The session isn't counting overtime and isn't a stopwatch
+    NothingToFinish,
+    /// Overtime can't be paused, only finished
+    InOvertime,
+    /// The preset has no durations to switch to
+    InvalidPreset,
+    Internal,
+    /// Sent by a server from before error codes existed, or with a code this
+    /// build doesn't know
+    #[serde(other)]
+    Unknown,
+}
+
+/// Why a request failed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "RequestErrorRepr")]
+pub struct RequestError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Older servers send errors as a bare message
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RequestErrorRepr {
+    Typed { code: ErrorCode, message: String },
+    Message(String),
+}
+
+impl From<RequestErrorRepr> for RequestError {
+    fn from(repr: RequestErrorRepr) -> Self {
+        match repr {
+            RequestErrorRepr::Typed { code, message } => Self { code, message },
+            RequestErrorRepr::Message(message) => Self {
+                code: ErrorCode::Unknown,
+                message,
+            },
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Response {
     Ok,
+    /// The request moved the timer from one state to another
+    Transition {
+        from: TimerState,
+        to: TimerState,
+    },
     Status(TimerStatus),
-    Error(String),
+    Error(RequestError),
     Pong,
+    Timers(Vec<String>),
+    Hello {
+        version: u32,
+        capabilities: Vec<String>,
+    },
+}
+
+impl Response {
+    pub fn error(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::Error(RequestError {
+            code,
+            message: message.into(),
+        })
+    }
 }
 
 #[derive(Deserialize)]
@@ -34,4 +274,79 @@ pub struct SetTaskRequest {
 #[derive(Deserialize)]
 pub struct SetPresetRequest {
     pub preset: Preset,
+    #[serde(default)]
+    pub next_session: bool,
+}
+
+#[derive(Deserialize)]
+pub struct SetAutoContinueRequest {
+    pub enabled: bool,
+    pub grace_secs: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct ExtendRequest {
+    pub secs: u64,
+}
+
+#[derive(Deserialize)]
+pub struct SetFlowModeRequest {
+    pub enabled: bool,
+}
+
+#[derive(Deserialize)]
+pub struct InterruptRequest {
+    pub kind: InterruptionKind,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct SessionNoteRequest {
+    pub note: Option<String>,
+    pub rating: Option<u8>,
+}
+
+#[derive(Deserialize)]
+pub struct SetModeRequest {
+    pub mode: TimerMode,
+}
+
+#[derive(Deserialize)]
+pub struct SetCycleRequest {
+    pub length: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_preset_accepts_both_forms() {
+        let request: Request = serde_json::from_str(r#"{"SetPreset":"Long"}"#).unwrap();
+        assert!(matches!(
+            request,
+            Request::SetPreset {
+                preset: Preset::Long,
+                next_session: false
+            }
+        ));
+        let request: Request =
+            serde_json::from_str(r#"{"SetPreset":{"preset":"52/17","next_session":true}}"#)
+                .unwrap();
+        assert!(matches!(
+            request,
+            Request::SetPreset {
+                preset: Preset::Custom(name),
+                next_session: true
+            } if name == "52/17"
+        ));
+        let request: Request = serde_json::from_str(r#"{"SetPreset":{"preset":"Short"}}"#).unwrap();
+        assert!(matches!(
+            request,
+            Request::SetPreset {
+                preset: Preset::Short,
+                next_session: false
+            }
+        ));
+    }
 }
@@ -1,85 +1,783 @@
 // Copyright (c) 2025 Yu-Wen Chen
 // Licensed under the MIT License (see LICENSE file)
 
+use crate::clock::Clock;
 use crate::db;
+use crate::db::events::OpenSession;
+use crate::db::goals::GoalProgress;
+use crate::db::schedule::ScheduleBlock;
+use crate::notify::{Notice, NoticeKind, Notifications};
+use crate::protocol::messages::{
+    CAPABILITIES, ErrorCode, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION, Push,
+};
 use crate::protocol::{Request, Response};
-use crate::timer::Timer;
+use crate::timer::{
+    DEFAULT_TIMER, Durations, LogEvent, MAX_GRACE_PERIOD, Preset, RunState, SessionProgress,
+    TerminationReason, Timer, TimerMode, TimerState,
+};
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime};
 use sqlx::SqlitePool;
+use std::collections::BTreeMap;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, broadcast};
 use tokio::time::{Duration, interval};
 
+/// What to do with a session that was still open when the server last stopped
+#[derive(Debug, Default, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum RecoveryPolicy {
+    /// Continue the session with the time it had left. Running sessions keep
+    /// counting through the downtime and are completed if they ran out.
+    #[default]
+    Resume,
+    /// Close the session with a Terminated event at its last recorded time
+    Terminate,
+}
+
+/// Today's goal progress, cached so status requests don't hit the database
+#[derive(Debug, Default)]
+struct GoalCache {
+    progress: Option<GoalProgress>,
+    day: Option<NaiveDate>,
+}
+
+/// Reloads the goal progress. Returns the progress if this reload is the one
+/// that reached the goal.
+async fn refresh_goal(
+    pool: &SqlitePool,
+    cache: &Mutex<GoalCache>,
+    today: NaiveDate,
+) -> Option<GoalProgress> {
+    let progress = match db::goals::get_progress(pool, today).await {
+        Ok(progress) => progress,
+        Err(e) => {
+            log::error!("Failed to load goal progress: {e}");
+            return None;
+        }
+    };
+    let mut cache = cache.lock().await;
+    let newly_reached = match (cache.progress, progress) {
+        (Some(old), Some(new)) => {
+            cache.day == Some(today)
+                && (old.kind, old.target) == (new.kind, new.target)
+                && !old.reached
+                && new.reached
+        }
+        _ => false,
+    };
+    cache.progress = progress;
+    cache.day = Some(today);
+    progress.filter(|_| newly_reached)
+}
+
+/// Answers a client's handshake with this server's version and capabilities
+fn hello(version: u32, capabilities: &[String]) -> Response {
+    log::debug!("Client speaks protocol {version} with capabilities {capabilities:?}");
+    if version < MIN_PROTOCOL_VERSION {
+        return Response::error(
+            ErrorCode::UnsupportedVersion,
+            format!(
+                "Protocol version {version} is no longer supported, \
+             this server speaks {PROTOCOL_VERSION} and accepts {MIN_PROTOCOL_VERSION} or later"
+            ),
+        );
+    }
+    Response::Hello {
+        version: PROTOCOL_VERSION,
+        capabilities: CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+    }
+}
+
+/// Reports how a request moved the timer
+fn transition(from: TimerState, timer: &Timer) -> Response {
+    Response::Transition {
+        from,
+        to: timer.state(),
+    }
+}
+
+/// The local day the daily stats and the goal count as today
+fn stats_day(clock: &dyn Clock) -> NaiveDate {
+    clock.local_now().date_naive()
+}
+
+/// Work-hours blocks, cached for the tick loop
+#[derive(Debug, Default)]
+struct ScheduleState {
+    blocks: Option<Vec<ScheduleBlock>>,
+    /// Whether the last tick was inside a block
+    in_block: bool,
+}
+
+impl ScheduleState {
+    async fn reload(&mut self, pool: &SqlitePool) {
+        match db::schedule::get_blocks(pool).await {
+            Ok(blocks) => self.blocks = Some(blocks),
+            Err(e) => log::error!("Failed to load schedule: {e}"),
+        }
+    }
+
+    /// Loads the blocks for the first time. A block that is already under way
+    /// counts as entered without a transition, so a restart in the middle of one
+    /// doesn't start a session on its own.
+    async fn load(&mut self, pool: &SqlitePool, now: NaiveDateTime) {
+        self.reload(pool).await;
+        let blocks = self.blocks.as_deref().unwrap_or_default();
+        self.in_block = db::schedule::active_block(blocks, now).is_some();
+    }
+
+    /// `Some(true)` when a block begins at `now`, `Some(false)` when one ends
+    fn transition(&mut self, now: NaiveDateTime) -> Option<bool> {
+        let blocks = self.blocks.as_deref().unwrap_or_default();
+        let in_block = db::schedule::active_block(blocks, now).is_some();
+        let changed = in_block != self.in_block;
+        self.in_block = in_block;
+        changed.then_some(in_block)
+    }
+}
+
+/// The server's timers by name; the default one always exists
+type Timers = BTreeMap<String, Arc<Mutex<Timer>>>;
+
 pub struct PomoServer {
-    timer: Arc<Mutex<Timer>>,
+    timers: Arc<Mutex<Timers>>,
+    pool: SqlitePool,
+    clock: Arc<dyn Clock>,
+    goal: Arc<Mutex<GoalCache>>,
+    schedule: Arc<Mutex<ScheduleState>>,
+    /// Applied to every timer; paused sessions are terminated after this long
+    max_pause: Option<Duration>,
+    /// Status changes and events for subscribed connections
+    pushes: broadcast::Sender<Push>,
 }
 
+/// Pushes that a subscriber can fall behind by before it misses some
+const PUSH_CAPACITY: usize = 64;
+/// Most timers one server runs, the default one included
+const MAX_TIMERS: usize = 32;
+/// Longest timer name, in characters
+pub(crate) const MAX_TIMER_NAME_LEN: usize = 64;
+
 impl PomoServer {
-    pub fn new(pool: SqlitePool) -> Self {
-        let timer = Arc::new(Mutex::new(Timer::new()));
-        let timer_clone = timer.clone();
+    pub fn new(
+        pool: SqlitePool,
+        clock: Arc<dyn Clock>,
+        notifications: Arc<Notifications>,
+        max_pause: Option<Duration>,
+    ) -> Self {
+        let mut default_timer = Timer::with_clock(clock.clone());
+        default_timer.set_max_pause(max_pause);
+        let default_timer = Arc::new(Mutex::new(default_timer));
+        let timers = Arc::new(Mutex::new(Timers::from([(
+            DEFAULT_TIMER.to_string(),
+            default_timer,
+        )])));
+        let goal = Arc::new(Mutex::new(GoalCache::default()));
+        let tick_timers = timers.clone();
+        let tick_pool = pool.clone();
+        let tick_clock = clock.clone();
+        let tick_goal = goal.clone();
+        let schedule = Arc::new(Mutex::new(ScheduleState::default()));
+        let tick_schedule = schedule.clone();
+        let (pushes, _) = broadcast::channel(PUSH_CAPACITY);
+        let tick_pushes = pushes.clone();
 
         tokio::spawn(async move {
             let mut interval = interval(Duration::from_secs(1));
             loop {
                 interval.tick().await;
-                let events: Vec<crate::timer::LogEvent> = {
-                    let mut timer_guard = timer_clone.lock().await;
+                let transition = {
+                    let mut schedule = tick_schedule.lock().await;
+                    let now = tick_clock.local_now().naive_local();
+                    if schedule.blocks.is_none() {
+                        schedule.load(&tick_pool, now).await;
+                    }
+                    schedule.transition(now)
+                };
+                let timers: Vec<_> = tick_timers
+                    .lock()
+                    .await
+                    .iter()
+                    .map(|(name, timer)| (name.clone(), timer.clone()))
+                    .collect();
+                let mut events: Vec<(String, LogEvent)> = Vec::new();
+                let mut notices = Vec::new();
+                // Timers whose state changed other than by counting down
+                let mut changed = Vec::new();
+                for (name, timer) in timers {
+                    let mut timer_guard = timer.lock().await;
+                    // The work-hours schedule drives the default timer
+                    if name == DEFAULT_TIMER {
+                        match transition {
+                            Some(true) => timer_guard.schedule_start(),
+                            Some(false) => timer_guard.schedule_stop(),
+                            None => {}
+                        }
+                    }
                     timer_guard.update();
-                    timer_guard.drain_events().collect()
-                }; // MutexGuard dropped here
-                for event in events {
-                    if let Err(e) = db::events::insert_event(&pool, &event).await {
+                    let notices_before = notices.len();
+                    notices.extend(timer_guard.drain_notices());
+                    let drained: Vec<_> = timer_guard.drain_events().collect();
+                    if !drained.is_empty() || notices.len() > notices_before {
+                        changed.push((name.clone(), timer_guard.get_timer_status()));
+                    }
+                    drop(timer_guard);
+                    events.extend(drained.into_iter().map(|e| (name.clone(), e)));
+                }
+                for (name, event) in &events {
+                    if let Err(e) = db::events::insert_event(&tick_pool, name, event).await {
                         log::error!("Failed to persist event: {e}");
                     }
+                    // Sending only fails when nobody is subscribed
+                    let _ = tick_pushes.send(Push::Event {
+                        timer: name.clone(),
+                        event: event.clone(),
+                    });
+                }
+
+                // Goal progress only moves when a session completes, or resets
+                // when the day changes
+                let today = stats_day(tick_clock.as_ref());
+                let completed = events.iter().find_map(|(_, e)| match e {
+                    LogEvent::Completed { task, .. } => Some(task.clone()),
+                    _ => None,
+                });
+                let stale = tick_goal.lock().await.day != Some(today);
+                if (completed.is_some() || stale)
+                    && let Some(progress) = refresh_goal(&tick_pool, &tick_goal, today).await
+                {
+                    notices.push(Notice {
+                        kind: NoticeKind::GoalReached,
+                        mode: TimerMode::Work,
+                        task: completed.unwrap_or_default(),
+                        detail: progress.summary(),
+                    });
+                }
+                let goal = tick_goal.lock().await.progress;
+                for (timer, mut status) in changed {
+                    status.goal = goal;
+                    let _ = tick_pushes.send(Push::Status { timer, status });
+                }
+
+                if !notices.is_empty() {
+                    // Backends may block (D-Bus, child processes)
+                    let notifications = notifications.clone();
+                    tokio::task::spawn_blocking(move || {
+                        for notice in &notices {
+                            notifications.dispatch(notice);
+                        }
+                    });
                 }
             }
         });
 
-        Self { timer }
+        Self {
+            timers,
+            pool,
+            clock,
+            goal,
+            schedule,
+            max_pause,
+            pushes,
+        }
+    }
+
+    /// The current time on the server's clock
+    pub fn now(&self) -> DateTime<Local> {
+        self.clock.local_now()
+    }
+
+    /// Receives status changes and events of every timer from now on
+    pub fn subscribe(&self) -> broadcast::Receiver<Push> {
+        self.pushes.subscribe()
+    }
+
+    /// Pushes the current status of the named timer to subscribers
+    async fn push_status(&self, name: &str, timer: &Timer) {
+        if self.pushes.receiver_count() == 0 {
+            return;
+        }
+        let mut status = timer.get_timer_status();
+        status.goal = self.goal.lock().await.progress;
+        let _ = self.pushes.send(Push::Status {
+            timer: name.to_string(),
+            status,
+        });
+    }
+
+    /// Reloads the cached work-hours blocks after the schedule was changed
+    pub async fn reload_schedule(&self) {
+        self.schedule.lock().await.reload(&self.pool).await;
+    }
+
+    /// Reloads the cached goal progress after the goal was changed
+    pub async fn refresh_goal(&self) {
+        refresh_goal(&self.pool, &self.goal, stats_day(self.clock.as_ref())).await;
+        let timers: Vec<_> = self
+            .timers
+            .lock()
+            .await
+            .iter()
+            .map(|(name, timer)| (name.clone(), timer.clone()))
+            .collect();
+        for (name, timer) in timers {
+            self.push_status(&name, &*timer.lock().await).await;
+        }
+    }
+
+    /// The named timer, and whether it was just created. Unless `create` is
+    /// set, a timer that doesn't exist yet is `NotFound` instead of being created.
+    async fn timer(&self, name: &str, create: bool) -> Result<(Arc<Mutex<Timer>>, bool), Response> {
+        let mut timers = self.timers.lock().await;
+        if let Some(timer) = timers.get(name) {
+            return Ok((timer.clone(), false));
+        }
+        if !create {
+            return Err(Response::error(
+                ErrorCode::NotFound,
+                format!("Unknown timer: {name}"),
+            ));
+        }
+        if timers.len() >= MAX_TIMERS {
+            return Err(Response::error(
+                ErrorCode::InvalidValue,
+                format!("At most {MAX_TIMERS} timers can run at once"),
+            ));
+        }
+        let mut timer = Timer::with_clock(self.clock.clone());
+        timer.set_max_pause(self.max_pause);
+        let timer = Arc::new(Mutex::new(timer));
+        timers.insert(name.to_string(), timer.clone());
+        Ok((timer, true))
+    }
+
+    /// Removes a named timer. A session it was running is terminated as if
+    /// it had been reset.
+    async fn delete_timer(&self, name: &str) -> Response {
+        if name == DEFAULT_TIMER {
+            return Response::error(
+                ErrorCode::InvalidValue,
+                "The default timer can't be deleted",
+            );
+        }
+        let Some(timer) = self.timers.lock().await.remove(name) else {
+            return Response::error(ErrorCode::NotFound, format!("Unknown timer: {name}"));
+        };
+        let mut timer = timer.lock().await;
+        timer.reset();
+        for event in timer.drain_events() {
+            if let Err(e) = db::events::insert_event(&self.pool, name, &event).await {
+                log::error!("Failed to persist event: {e}");
+            }
+            let _ = self.pushes.send(Push::Event {
+                timer: name.to_string(),
+                event,
+            });
+        }
+        Response::Ok
     }
 
     pub async fn process_request(&self, request: Request) -> Response {
-        let mut timer = self.timer.lock().await;
         match request {
-            Request::Ping => Response::Pong,
-            Request::Start => {
-                if timer.is_idle() || timer.is_paused() {
-                    timer.toggle();
+            Request::ForTimer { timer, request } => {
+                if matches!(*request, Request::ForTimer { .. }) {
+                    return Response::error(
+                        ErrorCode::InvalidRequest,
+                        "Timer requests can't be nested",
+                    );
                 }
-                Response::Ok
+                let timer = timer.trim();
+                if timer.is_empty() {
+                    return Response::error(
+                        ErrorCode::InvalidRequest,
+                        "Timer name must not be empty",
+                    );
+                }
+                if timer.chars().count() > MAX_TIMER_NAME_LEN {
+                    return Response::error(
+                        ErrorCode::InvalidValue,
+                        format!("Timer names can be at most {MAX_TIMER_NAME_LEN} characters"),
+                    );
+                }
+                self.process_timer_request(timer, *request).await
             }
-            Request::Pause => {
-                if timer.is_running() && !timer.is_paused() {
+            request => self.process_timer_request(DEFAULT_TIMER, request).await,
+        }
+    }
+
+    async fn process_timer_request(&self, name: &str, request: Request) -> Response {
+        // Requests that don't touch a timer
+        let request = match request {
+            Request::Ping => return Response::Pong,
+            Request::Hello {
+                version,
+                capabilities,
+            } => return hello(version, &capabilities),
+            Request::ListTimers => {
+                return Response::Timers(self.timers.lock().await.keys().cloned().collect());
+            }
+            Request::DeleteTimer => return self.delete_timer(name).await,
+            Request::SetSessionNote {
+                session_id,
+                note,
+                rating,
+            } => return self.set_session_note(&session_id, note, rating).await,
+            request => request,
+        };
+
+        // Custom presets are resolved before taking the timer lock
+        let custom_durs = match &request {
+            Request::SetPreset {
+                preset: Preset::Custom(name),
+                ..
+            } => match self.lookup_preset(name).await {
+                Ok(durs) => Some(durs),
+                Err(response) => return response,
+            },
+            _ => None,
+        };
+
+        let read_only = matches!(request, Request::GetStatus | Request::Subscribe);
+        let (timer_ref, created) = match self.timer(name, !read_only).await {
+            Ok(timer) => timer,
+            Err(response) => return response,
+        };
+        let mut timer = timer_ref.lock().await;
+        let from = timer.state();
+        let response = match request {
+            Request::Ping
+            | Request::Hello { .. }
+            | Request::ListTimers
+            | Request::DeleteTimer
+            | Request::SetSessionNote { .. }
+            | Request::ForTimer { .. } => unreachable!("handled above"),
+            Request::Start => match from.run {
+                RunState::Running => {
+                    Response::error(ErrorCode::AlreadyRunning, "The timer is already running")
+                }
+                RunState::Idle | RunState::Paused => {
                     timer.toggle();
+                    transition(from, &timer)
                 }
-                Response::Ok
-            }
+            },
+            Request::Pause => match from.run {
+                // Toggling would end the session instead of pausing it
+                RunState::Running if timer.is_overtime() => Response::error(
+                    ErrorCode::InOvertime,
+                    "Overtime can't be paused, finish the session instead",
+                ),
+                RunState::Running => {
+                    timer.toggle();
+                    transition(from, &timer)
+                }
+                RunState::Paused => {
+                    Response::error(ErrorCode::AlreadyPaused, "The timer is already paused")
+                }
+                RunState::Idle => Response::error(ErrorCode::NotRunning, "The timer isn't running"),
+            },
             Request::Reset => {
                 timer.reset();
-                Response::Ok
+                transition(from, &timer)
+            }
+            Request::Undo => {
+                if timer.undo() {
+                    transition(from, &timer)
+                } else {
+                    Response::error(ErrorCode::NothingToUndo, "Nothing to undo")
+                }
             }
             Request::SetTask(name) => {
                 timer.set_task_name(&name);
-                Response::Ok
+                transition(from, &timer)
             }
             Request::ChangeTask(name) => {
                 timer.change_task(&name);
-                Response::Ok
+                transition(from, &timer)
             }
-            Request::GetStatus => Response::Status(timer.get_timer_status()),
-            Request::SetPreset(preset) => {
-                timer.set_preset(preset);
-                Response::Ok
+            Request::CreateTimer => Response::Ok,
+            Request::GetStatus | Request::Subscribe => {
+                let mut status = timer.get_timer_status();
+                status.goal = self.goal.lock().await.progress;
+                Response::Status(status)
+            }
+            Request::SetPreset {
+                preset,
+                next_session,
+            } => match custom_durs.or_else(|| Durations::for_preset(&preset)) {
+                None => Response::error(
+                    ErrorCode::InvalidPreset,
+                    format!("Preset {preset} has no durations"),
+                ),
+                Some(durs) => {
+                    match preset {
+                        preset if next_session => timer.set_pending_preset(preset, durs),
+                        Preset::Custom(name) => timer.set_custom_preset(&name, durs),
+                        preset => timer.set_preset(preset),
+                    }
+                    transition(from, &timer)
+                }
+            },
+            Request::SetCycleLength(len) => {
+                if len == 0 {
+                    Response::error(ErrorCode::InvalidValue, "Cycle length must be at least 1")
+                } else {
+                    timer.set_cycle_length(len);
+                    Response::Ok
+                }
+            }
+            Request::SetAutoContinue(enabled) => {
+                timer.set_auto_continue(enabled);
+                transition(from, &timer)
+            }
+            Request::SetGracePeriod(secs) => {
+                let grace = Duration::from_secs(secs);
+                if grace > MAX_GRACE_PERIOD {
+                    Response::error(
+                        ErrorCode::InvalidValue,
+                        format!(
+                            "Grace period must be at most {} seconds",
+                            MAX_GRACE_PERIOD.as_secs()
+                        ),
+                    )
+                } else {
+                    timer.set_grace_period(grace);
+                    Response::Ok
+                }
+            }
+            Request::CancelGrace => {
+                if timer.cancel_grace() {
+                    Response::Ok
+                } else {
+                    Response::error(ErrorCode::NoPendingSession, "No session pending")
+                }
+            }
+            Request::SkipGrace => {
+                if timer.skip_grace() {
+                    transition(from, &timer)
+                } else {
+                    Response::error(ErrorCode::NoPendingSession, "No session pending")
+                }
+            }
+            Request::Extend(secs) => {
+                if secs == 0 {
+                    Response::error(ErrorCode::InvalidValue, "Extension must be positive")
+                } else if !timer.can_extend() {
+                    Response::error(ErrorCode::NoActiveSession, "No active session to extend")
+                } else if timer.extend(Duration::from_secs(secs)) {
+                    transition(from, &timer)
+                } else {
+                    Response::error(ErrorCode::InvalidValue, "Extension is too large")
+                }
+            }
+            Request::SetFlowMode(enabled) => {
+                timer.set_flow_mode(enabled);
+                transition(from, &timer)
+            }
+            Request::Finish => {
+                if timer.finish() {
+                    transition(from, &timer)
+                } else {
+                    Response::error(
+                        ErrorCode::NothingToFinish,
+                        "No overtime or stopwatch session to finish",
+                    )
+                }
+            }
+            Request::Interrupt { kind, note } => {
+                // An empty note from a prompt means no note
+                let note = note.filter(|n| !n.trim().is_empty());
+                if timer.interrupt(kind, note) {
+                    Response::Ok
+                } else {
+                    Response::error(ErrorCode::NoActiveSession, "No work session to interrupt")
+                }
+            }
+            Request::SetMode(mode) => {
+                timer.set_mode(mode);
+                transition(from, &timer)
             }
             Request::SwitchMode => {
                 timer.switch_mode();
-                Response::Ok
+                transition(from, &timer)
             }
             Request::Resume => {
-                if timer.is_paused() {
+                if from.run == RunState::Paused {
                     timer.toggle();
+                    transition(from, &timer)
+                } else {
+                    Response::error(ErrorCode::NotPaused, "The timer isn't paused")
                 }
-                Response::Ok
+            }
+        };
+        if let Response::Error(_) = response {
+            // A request that failed leaves no timer behind
+            drop(timer);
+            if created {
+                let mut timers = self.timers.lock().await;
+                if timers.get(name).is_some_and(|t| Arc::ptr_eq(t, &timer_ref)) {
+                    timers.remove(name);
+                }
+            }
+        } else if !read_only {
+            self.push_status(name, &timer).await;
+        }
+        response
+    }
+
+    /// Closes or resumes each timer's last open session according to
+    /// `policy`. Must run before any client request reaches the timers.
+    pub async fn recover(&self, policy: RecoveryPolicy) -> Result<()> {
+        for open in db::events::get_open_sessions(&self.pool).await? {
+            self.recover_session(open, policy).await?;
+        }
+        Ok(())
+    }
+
+    async fn recover_session(&self, open: OpenSession, policy: RecoveryPolicy) -> Result<()> {
+        let id = uuid::Uuid::parse_str(&open.session_id)?;
+        let task = open.task.clone().unwrap_or_default();
+        let last_at = DateTime::parse_from_rfc3339(&open.last_at)?.with_timezone(&Local);
+        let last_remaining = open.last_remaining_secs.unwrap_or(0).max(0) as u64;
+        let planned = (open.planned_secs.unwrap_or(0) + open.extended_secs).max(0) as u64;
+        let paused_secs = open.paused_secs.max(0) as u64;
+        let mode = open
+            .timer_type
+            .as_deref()
+            .and_then(|t| t.parse::<TimerMode>().ok());
+
+        let paused = open.final_event == "Paused";
+        let now = self.clock.local_now();
+        let down = (now - last_at).to_std().unwrap_or_default();
+        let stopwatch = mode == Some(TimerMode::Stopwatch);
+        // A stopwatch counts up to the restart unless it was left paused; a
+        // countdown ran for what it used of its planned time
+        let ran_until = if stopwatch && !paused { now } else { last_at };
+        let active_secs = if stopwatch {
+            let started_at = DateTime::parse_from_rfc3339(&open.started_at)?;
+            let elapsed = (ran_until.fixed_offset() - started_at).num_seconds().max(0);
+            (elapsed as u64).saturating_sub(paused_secs)
+        } else {
+            planned.saturating_sub(last_remaining)
+        };
+
+        let (Some(mode), RecoveryPolicy::Resume) = (mode, policy) else {
+            log::info!("Terminating session {id} left open by the previous run");
+            let event = LogEvent::Terminated {
+                id,
+                task,
+                at: ran_until,
+                remaining: last_remaining,
+                active_secs,
+                planned_secs: planned,
+                paused_secs,
+                reason: None,
+            };
+            return db::events::insert_event(&self.pool, &open.timer_name, &event).await;
+        };
+
+        if let Some(max) = self.max_pause.filter(|max| paused && down >= *max) {
+            log::info!("Session {id} was paused past the maximum pause, terminating it");
+            let event = LogEvent::Terminated {
+                id,
+                task,
+                at: last_at + chrono::Duration::from_std(max)?,
+                remaining: last_remaining,
+                active_secs,
+                planned_secs: planned,
+                paused_secs: paused_secs + max.as_secs(),
+                reason: Some(TerminationReason::MaxPause),
+            };
+            return db::events::insert_event(&self.pool, &open.timer_name, &event).await;
+        }
+        let remaining = if paused || stopwatch {
+            last_remaining
+        } else {
+            last_remaining.saturating_sub(down.as_secs())
+        };
+
+        if remaining == 0 && !stopwatch {
+            log::info!("Session {id} ran out while the server was down, completing it");
+            let event = LogEvent::Completed {
+                id,
+                task,
+                at: last_at + chrono::Duration::seconds(last_remaining as i64),
+                active_secs: planned,
+                planned_secs: planned,
+                paused_secs,
+            };
+            return db::events::insert_event(&self.pool, &open.timer_name, &event).await;
+        }
+
+        log::info!("Resuming session {id} with {remaining}s left");
+        let (timer, _) = self
+            .timer(&open.timer_name, true)
+            .await
+            .map_err(|response| anyhow::anyhow!("Can't restore session {id}: {response:?}"))?;
+        timer.lock().await.restore(
+            id,
+            mode,
+            &task,
+            SessionProgress {
+                planned: Duration::from_secs(planned),
+                remaining: Duration::from_secs(remaining),
+                paused: Duration::from_secs(paused_secs),
+                active: Duration::from_secs(if stopwatch {
+                    active_secs
+                } else {
+                    planned.saturating_sub(remaining)
+                }),
+            },
+            paused,
+        );
+        Ok(())
+    }
+
+    async fn set_session_note(
+        &self,
+        session_id: &str,
+        note: Option<String>,
+        rating: Option<u8>,
+    ) -> Response {
+        if rating.is_some_and(|r| !(1..=5).contains(&r)) {
+            return Response::error(ErrorCode::InvalidValue, "Rating must be between 1 and 5");
+        }
+        let note = note.filter(|n| !n.trim().is_empty());
+        let result = match db::notes::session_exists(&self.pool, session_id).await {
+            Ok(false) => {
+                return Response::error(
+                    ErrorCode::NotFound,
+                    format!("Unknown session: {session_id}"),
+                );
+            }
+            Ok(true) => {
+                db::notes::set_session_note(&self.pool, session_id, note.as_deref(), rating).await
+            }
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(()) => Response::Ok,
+            Err(e) => {
+                log::error!("Failed to save session note: {e}");
+                Response::error(ErrorCode::Internal, "Failed to save session note")
+            }
+        }
+    }
+
+    async fn lookup_preset(&self, name: &str) -> Result<Durations, Response> {
+        match db::presets::get_preset(&self.pool, name).await {
+            // Rows from before presets were validated can have zero durations
+            Ok(Some(row)) if row.work_secs <= 0 || row.break_secs <= 0 => Err(Response::error(
+                ErrorCode::InvalidPreset,
+                format!("Preset {name} has no durations"),
+            )),
+            Ok(Some(row)) => Ok(row.durations()),
+            Ok(None) => Err(Response::error(
+                ErrorCode::NotFound,
+                format!("Unknown preset: {name}"),
+            )),
+            Err(e) => {
+                log::error!("Preset lookup failed: {e}");
+                Err(Response::error(ErrorCode::Internal, "Preset lookup failed"))
             }
         }
     }
@@ -88,17 +786,17 @@ impl PomoServer {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::{ManualClock, SystemClock};
+    use crate::db::test_pool;
     use crate::protocol::{Request, Response};
-    use sqlx::pool::PoolOptions;
+    use crate::timer::InterruptionKind;
 
     async fn test_server() -> PomoServer {
-        let pool = PoolOptions::<sqlx::Sqlite>::new()
-            .max_connections(1)
-            .connect("sqlite::memory:")
-            .await
-            .unwrap();
-        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
-        PomoServer::new(pool)
+        test_server_with(test_pool().await, Arc::new(SystemClock))
+    }
+
+    fn test_server_with(pool: SqlitePool, clock: Arc<dyn Clock>) -> PomoServer {
+        PomoServer::new(pool, clock, Arc::new(Notifications::none()), None)
     }
 
     #[tokio::test]
@@ -108,17 +806,853 @@ mod tests {
         assert!(matches!(response, Response::Pong));
     }
 
+    #[tokio::test]
+    async fn test_hello() {
+        let server = test_server().await;
+        let response = server
+            .process_request(Request::Hello {
+                version: PROTOCOL_VERSION,
+                capabilities: vec!["undo".into()],
+            })
+            .await;
+        let Response::Hello {
+            version,
+            capabilities,
+        } = response
+        else {
+            panic!("expected hello");
+        };
+        assert_eq!(version, PROTOCOL_VERSION);
+        assert!(capabilities.iter().any(|c| c == "named_timers"));
+
+        let response = server
+            .process_request(Request::Hello {
+                version: MIN_PROTOCOL_VERSION - 1,
+                capabilities: Vec::new(),
+            })
+            .await;
+        assert!(matches!(response, Response::Error(_)));
+    }
+
     #[tokio::test]
     async fn test_preset() {
         use crate::timer::Preset;
         let server = test_server().await;
         let response = server
-            .process_request(Request::SetPreset(Preset::Long))
+            .process_request(Request::SetPreset {
+                preset: Preset::Long,
+                next_session: false,
+            })
             .await;
-        assert!(matches!(response, Response::Ok));
+        assert!(matches!(response, Response::Transition { .. }));
         let response = server.process_request(Request::GetStatus).await;
         if let Response::Status(status) = response {
             assert_eq!(status.preset, "Long");
         }
     }
+
+    #[tokio::test]
+    async fn test_pending_custom_preset() {
+        let pool = test_pool().await;
+        db::presets::upsert_preset(&pool, "52/17", 52 * 60, 17 * 60, None)
+            .await
+            .unwrap();
+        let server = test_server_with(pool, Arc::new(SystemClock));
+        server.process_request(Request::Start).await;
+        let response = server
+            .process_request(Request::SetPreset {
+                preset: Preset::Custom("52/17".into()),
+                next_session: true,
+            })
+            .await;
+        assert!(matches!(
+            response,
+            Response::Transition { from, to } if from == to && to.run == RunState::Running
+        ));
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert!(status.is_running);
+        assert_eq!(status.preset, "Short");
+        assert_eq!(status.pending_preset.as_deref(), Some("52/17"));
+    }
+
+    #[tokio::test]
+    async fn test_custom_preset() {
+        let pool = test_pool().await;
+        db::presets::upsert_preset(&pool, "52/17", 52 * 60, 17 * 60, None)
+            .await
+            .unwrap();
+        let server = test_server_with(pool, Arc::new(SystemClock));
+        let response = server
+            .process_request(Request::SetPreset {
+                preset: Preset::Custom("52/17".into()),
+                next_session: false,
+            })
+            .await;
+        assert!(matches!(response, Response::Transition { .. }));
+        let response = server.process_request(Request::GetStatus).await;
+        let Response::Status(status) = response else {
+            panic!("expected status");
+        };
+        assert_eq!(status.preset, "52/17");
+        assert_eq!(status.remaining, 52 * 60);
+    }
+
+    #[tokio::test]
+    async fn test_unknown_custom_preset() {
+        let server = test_server().await;
+        let response = server
+            .process_request(Request::SetPreset {
+                preset: Preset::Custom("nope".into()),
+                next_session: false,
+            })
+            .await;
+        assert!(matches!(response, Response::Error(_)));
+    }
+
+    #[tokio::test]
+    async fn test_preset_without_durations() {
+        let pool = test_pool().await;
+        db::presets::upsert_preset(&pool, "broken", 0, 0, None)
+            .await
+            .unwrap();
+        let server = test_server_with(pool, Arc::new(SystemClock));
+        let request = Request::SetPreset {
+            preset: Preset::Custom("broken".into()),
+            next_session: false,
+        };
+        assert_eq!(
+            error_code(&server, request).await,
+            Some(ErrorCode::InvalidPreset)
+        );
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert_eq!(status.preset, "Short");
+    }
+
+    #[tokio::test]
+    async fn test_settings_report_transition() {
+        let server = test_server().await;
+        server.process_request(Request::Start).await;
+        for request in [
+            Request::SetTask("write".into()),
+            Request::ChangeTask("review".into()),
+            Request::SetAutoContinue(false),
+            Request::SetFlowMode(true),
+        ] {
+            assert!(matches!(
+                server.process_request(request).await,
+                Response::Transition { from, to } if from == to && to.run == RunState::Running
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cycle_length() {
+        let server = test_server().await;
+        let response = server.process_request(Request::SetCycleLength(0)).await;
+        assert!(matches!(response, Response::Error(_)));
+        server.process_request(Request::SetCycleLength(2)).await;
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert_eq!(status.cycle_length, 2);
+        assert_eq!(status.cycle_position, 0);
+    }
+
+    #[tokio::test]
+    async fn test_auto_continue_and_grace() {
+        let server = test_server().await;
+        server
+            .process_request(Request::SetAutoContinue(false))
+            .await;
+        server.process_request(Request::SetGracePeriod(10)).await;
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert!(!status.auto_continue);
+        assert!(status.grace_remaining.is_none());
+        let response = server.process_request(Request::CancelGrace).await;
+        assert!(matches!(response, Response::Error(_)));
+
+        assert_eq!(
+            error_code(&server, Request::SetGracePeriod(u64::MAX)).await,
+            Some(ErrorCode::InvalidValue)
+        );
+        let response = server
+            .process_request(Request::SetGracePeriod(MAX_GRACE_PERIOD.as_secs()))
+            .await;
+        assert!(matches!(response, Response::Ok));
+    }
+
+    async fn open_session(pool: &SqlitePool, paused: bool, secs_ago: i64) -> uuid::Uuid {
+        let id = uuid::Uuid::new_v4();
+        let at = Local::now() - chrono::Duration::seconds(secs_ago);
+        db::events::insert_event(
+            pool,
+            DEFAULT_TIMER,
+            &LogEvent::Started {
+                id,
+                timer_type: TimerMode::Work,
+                task: "recovered".into(),
+                at,
+                remaining: 1500,
+                preset: None,
+            },
+        )
+        .await
+        .unwrap();
+        if paused {
+            db::events::insert_event(
+                pool,
+                DEFAULT_TIMER,
+                &LogEvent::Paused {
+                    id,
+                    task: "recovered".into(),
+                    at,
+                    remaining: 1000,
+                },
+            )
+            .await
+            .unwrap();
+        }
+        id
+    }
+
+    async fn final_event(pool: &SqlitePool, id: uuid::Uuid) -> String {
+        let row: (String,) =
+            sqlx::query_as("SELECT final_event FROM sessions WHERE session_id = ?")
+                .bind(id.to_string())
+                .fetch_one(pool)
+                .await
+                .unwrap();
+        row.0
+    }
+
+    #[tokio::test]
+    async fn test_recover_resumes_running_session() {
+        let pool = test_pool().await;
+        open_session(&pool, false, 100).await;
+        let server = test_server_with(pool, Arc::new(SystemClock));
+        server.recover(RecoveryPolicy::Resume).await.unwrap();
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert!(status.is_running);
+        assert_eq!(status.task, "recovered");
+        assert!((1398..=1400).contains(&status.remaining));
+    }
+
+    #[tokio::test]
+    async fn test_named_timers_are_independent() {
+        let server = test_server().await;
+        let alice = |request: Request| request.for_timer(Some("alice"));
+        server
+            .process_request(alice(Request::SetTask("report".into())))
+            .await;
+        server.process_request(alice(Request::Start)).await;
+
+        let Response::Status(status) = server.process_request(alice(Request::GetStatus)).await
+        else {
+            panic!("expected status");
+        };
+        assert!(status.is_running);
+        assert_eq!(status.task, "report");
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert!(!status.is_running);
+
+        let Response::Timers(names) = server.process_request(Request::ListTimers).await else {
+            panic!("expected timers");
+        };
+        assert_eq!(names, ["alice", "default"]);
+
+        let nested = alice(alice(Request::Start));
+        assert!(matches!(
+            server.process_request(nested).await,
+            Response::Error(_)
+        ));
+        let blank = Request::Start.for_timer(Some(" "));
+        assert!(matches!(
+            server.process_request(blank).await,
+            Response::Error(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_timer_limits() {
+        let server = test_server().await;
+        let bob = |request: Request| request.for_timer(Some("bob"));
+        // Reading a timer doesn't create it
+        assert_eq!(
+            error_code(&server, bob(Request::GetStatus)).await,
+            Some(ErrorCode::NotFound)
+        );
+        assert_eq!(
+            error_code(&server, bob(Request::Subscribe)).await,
+            Some(ErrorCode::NotFound)
+        );
+        assert!(matches!(
+            server.process_request(bob(Request::CreateTimer)).await,
+            Response::Ok
+        ));
+        assert!(matches!(
+            server.process_request(bob(Request::GetStatus)).await,
+            Response::Status(_)
+        ));
+
+        let long_name = "x".repeat(MAX_TIMER_NAME_LEN + 1);
+        assert_eq!(
+            error_code(&server, Request::Start.for_timer(Some(&long_name))).await,
+            Some(ErrorCode::InvalidValue)
+        );
+        for i in 2..MAX_TIMERS {
+            let request = Request::CreateTimer.for_timer(Some(&format!("timer{i}")));
+            assert!(matches!(
+                server.process_request(request).await,
+                Response::Ok
+            ));
+        }
+        assert_eq!(
+            error_code(&server, Request::Start.for_timer(Some("one too many"))).await,
+            Some(ErrorCode::InvalidValue)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_request_creates_no_timer() {
+        let server = test_server().await;
+        let carol = |request: Request| request.for_timer(Some("carol"));
+        assert_eq!(
+            error_code(&server, carol(Request::Pause)).await,
+            Some(ErrorCode::NotRunning)
+        );
+        assert_eq!(
+            error_code(&server, carol(Request::GetStatus)).await,
+            Some(ErrorCode::NotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_timer() {
+        let pool = test_pool().await;
+        let server = test_server_with(pool.clone(), Arc::new(SystemClock));
+        let dave = |request: Request| request.for_timer(Some("dave"));
+        assert_eq!(
+            error_code(&server, dave(Request::DeleteTimer)).await,
+            Some(ErrorCode::NotFound)
+        );
+        assert_eq!(
+            error_code(&server, Request::DeleteTimer).await,
+            Some(ErrorCode::InvalidValue)
+        );
+
+        server.process_request(dave(Request::Start)).await;
+        assert!(matches!(
+            server.process_request(dave(Request::DeleteTimer)).await,
+            Response::Ok
+        ));
+        let Response::Timers(names) = server.process_request(Request::ListTimers).await else {
+            panic!("expected timers");
+        };
+        assert_eq!(names, ["default"]);
+        // The running session is logged as ended, not left open
+        assert!(
+            db::events::get_open_sessions(&pool)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_recover_named_timer() {
+        let pool = test_pool().await;
+        let id = uuid::Uuid::new_v4();
+        db::events::insert_event(
+            &pool,
+            "alice",
+            &LogEvent::Started {
+                id,
+                timer_type: TimerMode::Work,
+                task: "recovered".into(),
+                at: Local::now(),
+                remaining: 1500,
+                preset: None,
+            },
+        )
+        .await
+        .unwrap();
+        let server = test_server_with(pool, Arc::new(SystemClock));
+        server.recover(RecoveryPolicy::Resume).await.unwrap();
+
+        let request = Request::GetStatus.for_timer(Some("alice"));
+        let Response::Status(status) = server.process_request(request).await else {
+            panic!("expected status");
+        };
+        assert!(status.is_running);
+        assert_eq!(status.task, "recovered");
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert!(!status.is_running);
+    }
+
+    #[tokio::test]
+    async fn test_recover_resumes_paused_session() {
+        let pool = test_pool().await;
+        open_session(&pool, true, 100).await;
+        let server = test_server_with(pool, Arc::new(SystemClock));
+        server.recover(RecoveryPolicy::Resume).await.unwrap();
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert!(status.is_paused);
+        assert_eq!(status.remaining, 1000);
+    }
+
+    #[tokio::test]
+    async fn test_recover_completes_expired_session() {
+        let pool = test_pool().await;
+        let id = open_session(&pool, false, 3600).await;
+        let server = test_server_with(pool.clone(), Arc::new(SystemClock));
+        server.recover(RecoveryPolicy::Resume).await.unwrap();
+        assert_eq!(final_event(&pool, id).await, "Completed");
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert!(status.is_idle);
+    }
+
+    #[tokio::test]
+    async fn test_recover_counts_downtime_on_server_clock() {
+        let pool = test_pool().await;
+        let id = open_session(&pool, false, 100).await;
+        // The session had 1400s left; the server clock says 2000s have passed
+        let clock = Arc::new(ManualClock::new());
+        clock.advance(Duration::from_secs(1900));
+        let server = test_server_with(pool.clone(), clock);
+        server.recover(RecoveryPolicy::Resume).await.unwrap();
+        assert_eq!(final_event(&pool, id).await, "Completed");
+    }
+
+    #[tokio::test]
+    async fn test_recover_terminates_session() {
+        let pool = test_pool().await;
+        let id = open_session(&pool, true, 100).await;
+        let server = test_server_with(pool.clone(), Arc::new(SystemClock));
+        server.recover(RecoveryPolicy::Terminate).await.unwrap();
+        assert_eq!(final_event(&pool, id).await, "Terminated");
+        let row: (i64,) = sqlx::query_as(
+            "SELECT active_secs FROM events WHERE session_id = ? AND event_type = 'Terminated'",
+        )
+        .bind(id.to_string())
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(row.0, 500);
+    }
+
+    #[tokio::test]
+    async fn test_recover_terminates_long_paused_session() {
+        let pool = test_pool().await;
+        let id = open_session(&pool, true, 3600).await;
+        let server = PomoServer::new(
+            pool.clone(),
+            Arc::new(SystemClock),
+            Arc::new(Notifications::none()),
+            Some(Duration::from_secs(1800)),
+        );
+        server.recover(RecoveryPolicy::Resume).await.unwrap();
+        assert_eq!(final_event(&pool, id).await, "Terminated");
+        let sessions = db::events::get_sessions(&pool, 1, None).await.unwrap();
+        assert_eq!(sessions[0].termination_reason.as_deref(), Some("max_pause"));
+        assert_eq!(sessions[0].paused_secs, 1800);
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert!(status.is_idle);
+    }
+
+    #[tokio::test]
+    async fn test_extend() {
+        let server = test_server().await;
+        let response = server.process_request(Request::Extend(60)).await;
+        assert!(matches!(response, Response::Error(_)));
+        server.process_request(Request::Start).await;
+        server.process_request(Request::Pause).await;
+        let response = server.process_request(Request::Extend(60)).await;
+        assert!(matches!(
+            response,
+            Response::Transition { to, .. } if to.run == RunState::Paused
+        ));
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert!((25 * 60 + 59..=25 * 60 + 60).contains(&status.remaining));
+
+        assert_eq!(
+            error_code(&server, Request::Extend(0)).await,
+            Some(ErrorCode::InvalidValue)
+        );
+        assert_eq!(
+            error_code(&server, Request::Extend(u64::MAX)).await,
+            Some(ErrorCode::InvalidValue)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pause_in_overtime() {
+        let clock = Arc::new(ManualClock::new());
+        let server = test_server_with(test_pool().await, clock.clone());
+        server
+            .process_request(Request::SetPreset {
+                preset: Preset::Test,
+                next_session: false,
+            })
+            .await;
+        server.process_request(Request::SetFlowMode(true)).await;
+        server.process_request(Request::Start).await;
+        clock.advance(Duration::from_secs(10));
+        // Tick the way the tick loop does, so the session notices it reached zero
+        let (timer, _) = server.timer(DEFAULT_TIMER, false).await.unwrap();
+        timer.lock().await.update();
+
+        // Pausing doesn't end the session
+        assert_eq!(
+            error_code(&server, Request::Pause).await,
+            Some(ErrorCode::InOvertime)
+        );
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert!(status.is_running);
+        assert_eq!(status.overtime, 5);
+    }
+
+    /// The error code a request failed with
+    async fn error_code(server: &PomoServer, request: Request) -> Option<ErrorCode> {
+        match server.process_request(request).await {
+            Response::Error(e) => Some(e.code),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_transitions_and_error_codes() {
+        let server = test_server().await;
+        let code = |request| error_code(&server, request);
+        assert_eq!(code(Request::Pause).await, Some(ErrorCode::NotRunning));
+        assert_eq!(code(Request::Resume).await, Some(ErrorCode::NotPaused));
+
+        let response = server.process_request(Request::Start).await;
+        let Response::Transition { from, to } = response else {
+            panic!("expected a transition");
+        };
+        assert_eq!(from.run, RunState::Idle);
+        assert_eq!(to.run, RunState::Running);
+        assert_eq!(to.mode, TimerMode::Work);
+        assert_eq!(code(Request::Start).await, Some(ErrorCode::AlreadyRunning));
+
+        server.process_request(Request::Pause).await;
+        assert_eq!(code(Request::Pause).await, Some(ErrorCode::AlreadyPaused));
+        let response = server.process_request(Request::Start).await;
+        assert!(matches!(
+            response,
+            Response::Transition {
+                from: TimerState {
+                    run: RunState::Paused,
+                    ..
+                },
+                to: TimerState {
+                    run: RunState::Running,
+                    ..
+                },
+            }
+        ));
+
+        let response = server.process_request(Request::SwitchMode).await;
+        let Response::Transition { from, to } = response else {
+            panic!("expected a transition");
+        };
+        assert_eq!(from.mode, TimerMode::Work);
+        assert_eq!(to.mode, TimerMode::Break);
+        assert_eq!(
+            code(Request::SetCycleLength(0)).await,
+            Some(ErrorCode::InvalidValue)
+        );
+        assert_eq!(
+            code(Request::Finish).await,
+            Some(ErrorCode::NothingToFinish)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_undo_reset() {
+        let server = test_server().await;
+        let response = server.process_request(Request::Undo).await;
+        assert!(matches!(response, Response::Error(_)));
+        server.process_request(Request::Start).await;
+        server.process_request(Request::Reset).await;
+        let response = server.process_request(Request::Undo).await;
+        assert!(matches!(
+            response,
+            Response::Transition {
+                from: TimerState {
+                    run: RunState::Idle,
+                    ..
+                },
+                to: TimerState {
+                    run: RunState::Running,
+                    ..
+                },
+            }
+        ));
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert!(status.is_running);
+    }
+
+    #[tokio::test]
+    async fn test_flow_mode() {
+        let server = test_server().await;
+        server.process_request(Request::SetFlowMode(true)).await;
+        server.process_request(Request::Start).await;
+        let response = server.process_request(Request::Finish).await;
+        assert!(matches!(response, Response::Error(_)));
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert!(status.flow_mode);
+        assert_eq!(status.overtime, 0);
+    }
+
+    #[tokio::test]
+    async fn test_interrupt() {
+        let server = test_server().await;
+        let interrupt = || Request::Interrupt {
+            kind: InterruptionKind::External,
+            note: Some(" ".into()),
+        };
+        let response = server.process_request(interrupt()).await;
+        assert!(matches!(response, Response::Error(_)));
+        server.process_request(Request::Start).await;
+        let response = server.process_request(interrupt()).await;
+        assert!(matches!(response, Response::Ok));
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert_eq!(status.interruptions, 1);
+        assert!(status.is_running);
+    }
+
+    #[tokio::test]
+    async fn test_session_note() {
+        let pool = test_pool().await;
+        let id = open_session(&pool, true, 100).await.to_string();
+        let server = test_server_with(pool.clone(), Arc::new(SystemClock));
+        let note = |session_id: &str, rating| Request::SetSessionNote {
+            session_id: session_id.into(),
+            note: Some("outlined chapter 2".into()),
+            rating,
+        };
+
+        let response = server.process_request(note(&id, Some(0))).await;
+        assert!(matches!(response, Response::Error(_)));
+        let response = server.process_request(note("nope", Some(3))).await;
+        assert!(matches!(response, Response::Error(e) if e.code == ErrorCode::NotFound));
+        let response = server.process_request(note(&id, Some(5))).await;
+        assert!(matches!(response, Response::Ok));
+
+        let sessions = db::events::get_sessions(&pool, 1, None).await.unwrap();
+        assert_eq!(sessions[0].note.as_deref(), Some("outlined chapter 2"));
+        assert_eq!(sessions[0].rating, Some(5));
+    }
+
+    #[tokio::test]
+    async fn test_goal_reached_once() {
+        use db::goals::{DailyGoal, GoalKind};
+
+        let pool = test_pool().await;
+        let goal = DailyGoal {
+            kind: GoalKind::Sessions,
+            target: 1,
+        };
+        db::goals::set_goal(&pool, &goal).await.unwrap();
+        let cache = Mutex::new(GoalCache::default());
+        let today = stats_day(&SystemClock);
+        assert!(refresh_goal(&pool, &cache, today).await.is_none());
+
+        db::insert_completed_session(&pool, TimerMode::Work, "t", 1500).await;
+        let reached = refresh_goal(&pool, &cache, today).await.unwrap();
+        assert_eq!(reached.summary(), "1/1 sessions");
+        // Already reached; no second notification
+        assert!(refresh_goal(&pool, &cache, today).await.is_none());
+        assert!(cache.lock().await.progress.unwrap().reached);
+    }
+
+    #[test]
+    fn test_schedule_transitions() {
+        let date = chrono::NaiveDate::from_ymd_opt(2026, 3, 30).unwrap(); // Monday
+        let at = |h, m| date.and_hms_opt(h, m, 0).unwrap();
+        let mut state = ScheduleState {
+            blocks: Some(vec![ScheduleBlock {
+                id: 1,
+                weekday: chrono::Weekday::Mon,
+                start: at(9, 0).time(),
+                end: at(12, 0).time(),
+            }]),
+            in_block: false,
+        };
+        assert_eq!(state.transition(at(8, 59)), None);
+        assert_eq!(state.transition(at(9, 0)), Some(true));
+        assert_eq!(state.transition(at(11, 0)), None);
+        assert_eq!(state.transition(at(12, 0)), Some(false));
+        assert_eq!(state.transition(at(13, 0)), None);
+    }
+
+    #[tokio::test]
+    async fn test_schedule_load_inside_block() {
+        let pool = test_pool().await;
+        let date = chrono::NaiveDate::from_ymd_opt(2026, 3, 30).unwrap(); // Monday
+        let at = |h, m| date.and_hms_opt(h, m, 0).unwrap();
+        db::schedule::create_block(
+            &pool,
+            chrono::Weekday::Mon,
+            at(9, 0).time(),
+            at(12, 0).time(),
+        )
+        .await
+        .unwrap();
+        let mut state = ScheduleState::default();
+        state.load(&pool, at(10, 0)).await;
+        // Already inside the block: no start, but its end still stops the timer
+        assert_eq!(state.transition(at(10, 0)), None);
+        assert_eq!(state.transition(at(12, 0)), Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_recover_terminates_stopwatch() {
+        let pool = test_pool().await;
+        let id = uuid::Uuid::new_v4();
+        let started = Local::now() - chrono::Duration::seconds(600);
+        for event in [
+            LogEvent::Started {
+                id,
+                timer_type: TimerMode::Stopwatch,
+                task: "untimed".into(),
+                at: started,
+                remaining: 0,
+                preset: None,
+            },
+            LogEvent::Paused {
+                id,
+                task: "untimed".into(),
+                at: started + chrono::Duration::seconds(240),
+                remaining: 0,
+            },
+            LogEvent::Resumed {
+                id,
+                task: "untimed".into(),
+                at: started + chrono::Duration::seconds(300),
+                remaining: 0,
+            },
+            LogEvent::Paused {
+                id,
+                task: "untimed".into(),
+                at: started + chrono::Duration::seconds(400),
+                remaining: 0,
+            },
+        ] {
+            db::events::insert_event(&pool, DEFAULT_TIMER, &event)
+                .await
+                .unwrap();
+        }
+        let server = test_server_with(pool.clone(), Arc::new(SystemClock));
+        server.recover(RecoveryPolicy::Terminate).await.unwrap();
+        assert_eq!(final_event(&pool, id).await, "Terminated");
+        let sessions = db::events::get_sessions(&pool, 1, None).await.unwrap();
+        // The minute spent paused in between is not counted
+        assert_eq!(sessions[0].work_secs, Some(340));
+    }
+
+    /// A stopwatch started ten minutes ago that was still running
+    async fn running_stopwatch(pool: &SqlitePool) -> uuid::Uuid {
+        let id = uuid::Uuid::new_v4();
+        let event = LogEvent::Started {
+            id,
+            timer_type: TimerMode::Stopwatch,
+            task: "untimed".into(),
+            at: Local::now() - chrono::Duration::seconds(600),
+            remaining: 0,
+            preset: None,
+        };
+        db::events::insert_event(pool, DEFAULT_TIMER, &event)
+            .await
+            .unwrap();
+        id
+    }
+
+    #[tokio::test]
+    async fn test_recover_running_stopwatch() {
+        let pool = test_pool().await;
+        running_stopwatch(&pool).await;
+        let server = test_server_with(pool.clone(), Arc::new(SystemClock));
+        server.recover(RecoveryPolicy::Resume).await.unwrap();
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert!(status.is_running);
+        assert_eq!(status.mode, "Stopwatch");
+        assert!((600..=601).contains(&status.overtime));
+        assert!(matches!(
+            server.process_request(Request::Finish).await,
+            Response::Transition { .. }
+        ));
+
+        // Terminating counts the time up to the restart
+        let pool = test_pool().await;
+        running_stopwatch(&pool).await;
+        let server = test_server_with(pool.clone(), Arc::new(SystemClock));
+        server.recover(RecoveryPolicy::Terminate).await.unwrap();
+        let sessions = db::events::get_sessions(&pool, 1, None).await.unwrap();
+        assert!((600..=601).contains(&sessions[0].work_secs.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_stopwatch() {
+        let server = test_server().await;
+        server
+            .process_request(Request::SetMode(TimerMode::Stopwatch))
+            .await;
+        server.process_request(Request::Start).await;
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert_eq!(status.mode, "Stopwatch");
+        assert!(status.is_running);
+        let response = server.process_request(Request::Finish).await;
+        assert!(matches!(
+            response,
+            Response::Transition {
+                from: TimerState {
+                    mode: TimerMode::Stopwatch,
+                    run: RunState::Running,
+                },
+                to: TimerState {
+                    mode: TimerMode::Work,
+                    run: RunState::Idle,
+                },
+            }
+        ));
+        let Response::Status(status) = server.process_request(Request::GetStatus).await else {
+            panic!("expected status");
+        };
+        assert_eq!(status.mode, "Work");
+        assert!(status.is_idle);
+    }
 }
@@ -7,8 +7,9 @@ use axum::{
     extract::{Path, Query, State},
     http::StatusCode,
     response::{Html, Json},
-    routing::{delete, get, post, put},
+    routing::{delete, get, patch, post, put},
 };
+use chrono::{DateTime, Local, NaiveTime, Weekday};
 use serde::Deserialize;
 use serde_json::{Value, json};
 use sqlx::SqlitePool;
@@ -17,8 +18,15 @@ use tokio::net::TcpListener;
 
 use crate::{
     db,
-    protocol::messages::{SetPresetRequest, SetTaskRequest},
-    server::core::PomoServer,
+    protocol::{
+        Response,
+        messages::{
+            ErrorCode, ExtendRequest, InterruptRequest, SessionNoteRequest, SetAutoContinueRequest,
+            SetCycleRequest, SetFlowModeRequest, SetModeRequest, SetPresetRequest, SetTaskRequest,
+        },
+    },
+    server::core::{MAX_TIMER_NAME_LEN, PomoServer},
+    timer::{DEFAULT_TIMER, Durations, Preset, TimerMode},
 };
 
 #[derive(Clone)]
@@ -39,31 +47,7 @@ impl HttpServer {
     }
 
     pub async fn start(&self, addr: &str) -> Result<()> {
-        let app = Router::new()
-            .route("/ping", get(ping_handler))
-            .route("/timer/status", get(get_status_handler))
-            .route("/timer/start", post(start_timer_handler))
-            .route("/timer/pause", post(pause_timer_handler))
-            .route("/timer/resume", post(resume_timer_handler))
-            .route("/timer/reset", post(reset_timer_handler))
-            .route("/timer/switch", post(switch_mode_timer_handler))
-            .route("/timer/task", put(set_task_handler))
-            .route("/timer/preset", put(set_preset_handler))
-            .route("/timer/history", get(get_history_handler))
-            // Todo endpoints
-            .route("/todos", get(get_todos_handler))
-            .route("/todos", post(create_todo_handler))
-            .route("/todos/{id}", put(update_todo_handler))
-            .route("/todos/{id}", delete(delete_todo_handler))
-            .route("/todos/{id}/toggle", post(toggle_todo_handler))
-            .route("/todos/{id}/priority", post(cycle_todo_priority_handler))
-            .route("/todos/{id}/stats", get(get_todo_stats_handler))
-            // Stats
-            .route("/stats/daily", get(get_daily_stats_handler))
-            // Dashboard
-            .route("/", get(dashboard_handler))
-            .with_state(self.state.clone());
-
+        let app = router(self.state.clone());
         let listener = TcpListener::bind(addr).await?;
         eprintln!("HttpServer listening on {}", addr);
         axum::serve(listener, app).await?;
@@ -71,6 +55,106 @@ impl HttpServer {
     }
 }
 
+/// The HTTP API's routes
+fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/ping", get(ping_handler))
+        .route("/timers", get(list_timers_handler))
+        .route("/timers/{name}", delete(delete_timer_handler))
+        .route("/timer/status", get(get_status_handler))
+        .route("/timer/start", post(start_timer_handler))
+        .route("/timer/pause", post(pause_timer_handler))
+        .route("/timer/resume", post(resume_timer_handler))
+        .route("/timer/reset", post(reset_timer_handler))
+        .route("/timer/undo", post(undo_timer_handler))
+        .route("/timer/switch", post(switch_mode_timer_handler))
+        .route("/timer/mode", put(set_mode_handler))
+        .route("/timer/task", put(set_task_handler))
+        .route("/timer/preset", put(set_preset_handler))
+        .route("/timer/cycle", put(set_cycle_handler))
+        .route("/timer/auto-continue", put(set_auto_continue_handler))
+        .route("/timer/grace/cancel", post(cancel_grace_handler))
+        .route("/timer/grace/skip", post(skip_grace_handler))
+        .route("/timer/extend", post(extend_timer_handler))
+        .route("/timer/flow", put(set_flow_mode_handler))
+        .route("/timer/finish", post(finish_timer_handler))
+        .route("/timer/interrupt", post(interrupt_timer_handler))
+        .route("/timer/history", get(get_history_handler))
+        .route("/sessions", post(create_session_handler))
+        .route("/sessions/{id}", patch(update_session_handler))
+        .route("/sessions/{id}/note", get(get_session_note_handler))
+        .route("/sessions/{id}/note", put(set_session_note_handler))
+        // Presets
+        .route("/presets", get(get_presets_handler))
+        .route("/presets", post(create_preset_handler))
+        .route("/presets/{name}", delete(delete_preset_handler))
+        // Todo endpoints
+        .route("/todos", get(get_todos_handler))
+        .route("/todos", post(create_todo_handler))
+        .route("/todos/{id}", put(update_todo_handler))
+        .route("/todos/{id}", delete(delete_todo_handler))
+        .route("/todos/{id}/toggle", post(toggle_todo_handler))
+        .route("/todos/{id}/priority", post(cycle_todo_priority_handler))
+        .route("/todos/{id}/stats", get(get_todo_stats_handler))
+        // Stats
+        .route("/stats/daily", get(get_daily_stats_handler))
+        .route("/stats/breaks", get(get_break_stats_handler))
+        .route("/goals", get(get_goal_handler))
+        .route("/goals", put(set_goal_handler))
+        .route("/goals", delete(delete_goal_handler))
+        // Schedule endpoints
+        .route("/schedule", get(get_schedule_handler))
+        .route("/schedule", post(create_schedule_handler))
+        .route("/schedule/{id}", put(update_schedule_handler))
+        .route("/schedule/{id}", delete(delete_schedule_handler))
+        // Dashboard
+        .route("/", get(dashboard_handler))
+        .with_state(state)
+}
+
+/// Addresses a `/timer` request to a named timer, e.g. `?timer=alice`
+#[derive(Deserialize)]
+struct TimerQuery {
+    timer: Option<String>,
+}
+
+/// Replies to a timer request. Errors get the HTTP status their code stands
+/// for; transitions report the timer's state before and after.
+fn timer_reply(response: Response) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match response {
+        Response::Error(e) => match error_status(e.code) {
+            // The server logged the details
+            StatusCode::INTERNAL_SERVER_ERROR => Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "internal error", "code": e.code})),
+            )),
+            status => Err((status, Json(json!({"error": e.message, "code": e.code})))),
+        },
+        Response::Transition { from, to } => {
+            Ok(Json(json!({"success": true, "from": from, "to": to})))
+        }
+        _ => Ok(Json(json!({"success": true}))),
+    }
+}
+
+fn error_status(code: ErrorCode) -> StatusCode {
+    match code {
+        ErrorCode::InvalidRequest | ErrorCode::UnsupportedVersion => StatusCode::BAD_REQUEST,
+        ErrorCode::InvalidValue | ErrorCode::InvalidPreset => StatusCode::UNPROCESSABLE_ENTITY,
+        ErrorCode::NotFound => StatusCode::NOT_FOUND,
+        ErrorCode::AlreadyRunning
+        | ErrorCode::AlreadyPaused
+        | ErrorCode::NotRunning
+        | ErrorCode::NotPaused
+        | ErrorCode::NothingToUndo
+        | ErrorCode::NoPendingSession
+        | ErrorCode::NoActiveSession
+        | ErrorCode::NothingToFinish
+        | ErrorCode::InOvertime => StatusCode::CONFLICT,
+        ErrorCode::Internal | ErrorCode::Unknown => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
 async fn ping_handler(State(state): State<AppState>) -> Json<Value> {
     state
         .server
@@ -79,79 +163,477 @@ async fn ping_handler(State(state): State<AppState>) -> Json<Value> {
     Json(json!({"message": "pong"}))
 }
 
-async fn get_status_handler(State(state): State<AppState>) -> Json<Value> {
+async fn get_status_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     let response = state
         .server
-        .process_request(crate::protocol::Request::GetStatus)
+        .process_request(crate::protocol::Request::GetStatus.for_timer(query.timer.as_deref()))
         .await;
-    Json(serde_json::to_value(response).unwrap())
+    match response {
+        Response::Error(_) => timer_reply(response),
+        response => Ok(Json(serde_json::to_value(response).unwrap())),
+    }
 }
 
-async fn start_timer_handler(State(state): State<AppState>) -> Json<Value> {
-    state
+async fn start_timer_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
         .server
-        .process_request(crate::protocol::Request::Start)
+        .process_request(crate::protocol::Request::Start.for_timer(query.timer.as_deref()))
         .await;
-    Json(json!({"success": true}))
+    timer_reply(response)
 }
 
-async fn pause_timer_handler(State(state): State<AppState>) -> Json<Value> {
-    state
+async fn pause_timer_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
         .server
-        .process_request(crate::protocol::Request::Pause)
+        .process_request(crate::protocol::Request::Pause.for_timer(query.timer.as_deref()))
         .await;
-    Json(json!({"success": true}))
+    timer_reply(response)
 }
 
-async fn resume_timer_handler(State(state): State<AppState>) -> Json<Value> {
-    state
+async fn resume_timer_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
         .server
-        .process_request(crate::protocol::Request::Resume)
+        .process_request(crate::protocol::Request::Resume.for_timer(query.timer.as_deref()))
         .await;
-    Json(json!({"success": true}))
+    timer_reply(response)
 }
 
-async fn reset_timer_handler(State(state): State<AppState>) -> Json<Value> {
-    state
+async fn reset_timer_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
         .server
-        .process_request(crate::protocol::Request::Reset)
+        .process_request(crate::protocol::Request::Reset.for_timer(query.timer.as_deref()))
         .await;
-    Json(json!({"success": true}))
+    timer_reply(response)
 }
 
-async fn switch_mode_timer_handler(State(state): State<AppState>) -> Json<Value> {
-    state
+async fn switch_mode_timer_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
+        .server
+        .process_request(crate::protocol::Request::SwitchMode.for_timer(query.timer.as_deref()))
+        .await;
+    timer_reply(response)
+}
+
+async fn set_mode_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+    Json(req): Json<SetModeRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
         .server
-        .process_request(crate::protocol::Request::SwitchMode)
+        .process_request(
+            crate::protocol::Request::SetMode(req.mode).for_timer(query.timer.as_deref()),
+        )
         .await;
-    Json(json!({"success": true}))
+    timer_reply(response)
 }
 
 async fn set_task_handler(
     State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
     Json(req): Json<SetTaskRequest>,
-) -> Json<Value> {
-    state
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
         .server
-        .process_request(crate::protocol::Request::SetTask(req.task))
+        .process_request(
+            crate::protocol::Request::SetTask(req.task).for_timer(query.timer.as_deref()),
+        )
         .await;
-    Json(json!({"success": true}))
+    timer_reply(response)
 }
 
 async fn set_preset_handler(
     State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
     Json(req): Json<SetPresetRequest>,
-) -> Json<Value> {
-    state
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
+        .server
+        .process_request(
+            crate::protocol::Request::SetPreset {
+                preset: req.preset,
+                next_session: req.next_session,
+            }
+            .for_timer(query.timer.as_deref()),
+        )
+        .await;
+    timer_reply(response)
+}
+
+async fn set_cycle_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+    Json(req): Json<SetCycleRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
+        .server
+        .process_request(
+            crate::protocol::Request::SetCycleLength(req.length).for_timer(query.timer.as_deref()),
+        )
+        .await;
+    timer_reply(response)
+}
+
+async fn set_auto_continue_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+    Json(req): Json<SetAutoContinueRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    // The grace period is validated first so a bad one changes nothing
+    if let Some(secs) = req.grace_secs {
+        let response = state
+            .server
+            .process_request(
+                crate::protocol::Request::SetGracePeriod(secs).for_timer(query.timer.as_deref()),
+            )
+            .await;
+        let _ = timer_reply(response)?;
+    }
+    let response = state
+        .server
+        .process_request(
+            crate::protocol::Request::SetAutoContinue(req.enabled)
+                .for_timer(query.timer.as_deref()),
+        )
+        .await;
+    timer_reply(response)
+}
+
+async fn cancel_grace_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
+        .server
+        .process_request(crate::protocol::Request::CancelGrace.for_timer(query.timer.as_deref()))
+        .await;
+    timer_reply(response)
+}
+
+async fn undo_timer_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
+        .server
+        .process_request(crate::protocol::Request::Undo.for_timer(query.timer.as_deref()))
+        .await;
+    timer_reply(response)
+}
+
+async fn skip_grace_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
+        .server
+        .process_request(crate::protocol::Request::SkipGrace.for_timer(query.timer.as_deref()))
+        .await;
+    timer_reply(response)
+}
+
+async fn extend_timer_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+    Json(req): Json<ExtendRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
+        .server
+        .process_request(
+            crate::protocol::Request::Extend(req.secs).for_timer(query.timer.as_deref()),
+        )
+        .await;
+    timer_reply(response)
+}
+
+async fn set_flow_mode_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+    Json(req): Json<SetFlowModeRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
+        .server
+        .process_request(
+            crate::protocol::Request::SetFlowMode(req.enabled).for_timer(query.timer.as_deref()),
+        )
+        .await;
+    timer_reply(response)
+}
+
+async fn finish_timer_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
+        .server
+        .process_request(crate::protocol::Request::Finish.for_timer(query.timer.as_deref()))
+        .await;
+    timer_reply(response)
+}
+
+async fn interrupt_timer_handler(
+    State(state): State<AppState>,
+    Query(query): Query<TimerQuery>,
+    Json(req): Json<InterruptRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
+        .server
+        .process_request(
+            crate::protocol::Request::Interrupt {
+                kind: req.kind,
+                note: req.note,
+            }
+            .for_timer(query.timer.as_deref()),
+        )
+        .await;
+    timer_reply(response)
+}
+
+async fn list_timers_handler(State(state): State<AppState>) -> Json<Value> {
+    match state
+        .server
+        .process_request(crate::protocol::Request::ListTimers)
+        .await
+    {
+        Response::Timers(names) => Json(json!(names)),
+        response => Json(serde_json::to_value(response).unwrap()),
+    }
+}
+
+async fn delete_timer_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
+        .server
+        .process_request(crate::protocol::Request::DeleteTimer.for_timer(Some(&name)))
+        .await;
+    timer_reply(response)
+}
+
+// --- Manual session endpoints ---
+
+#[derive(Deserialize)]
+struct CreateSessionRequest {
+    started_at: DateTime<Local>,
+    duration_secs: u64,
+    #[serde(default)]
+    mode: TimerMode,
+    #[serde(default)]
+    task: String,
+    timer: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct UpdateSessionRequest {
+    started_at: Option<DateTime<Local>>,
+    ended_at: Option<DateTime<Local>>,
+    task: Option<String>,
+}
+
+/// Longest session that can be logged or corrected after the fact
+const MAX_MANUAL_SESSION_SECS: u64 = 24 * 60 * 60;
+
+fn too_long_session() -> (StatusCode, Json<Value>) {
+    (
+        StatusCode::UNPROCESSABLE_ENTITY,
+        Json(json!({"error": "session must last at most a day"})),
+    )
+}
+
+fn check_session_range(
+    started_at: DateTime<Local>,
+    ended_at: DateTime<Local>,
+    now: DateTime<Local>,
+) -> Result<(), (StatusCode, Json<Value>)> {
+    if ended_at <= started_at {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "session must end after it starts"})),
+        ));
+    }
+    if ended_at > now {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "session must end in the past"})),
+        ));
+    }
+    if (ended_at - started_at).num_seconds() as u64 > MAX_MANUAL_SESSION_SECS {
+        return Err(too_long_session());
+    }
+    Ok(())
+}
+
+/// Logs a session that was never timed, e.g. because the timer wasn't started
+async fn create_session_handler(
+    State(state): State<AppState>,
+    Json(req): Json<CreateSessionRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let ended_at = Some(req.duration_secs)
+        .filter(|&secs| secs <= MAX_MANUAL_SESSION_SECS)
+        .and_then(|secs| chrono::Duration::try_seconds(secs as i64))
+        .and_then(|length| req.started_at.checked_add_signed(length))
+        .ok_or_else(too_long_session)?;
+    let duration = std::time::Duration::from_secs(req.duration_secs);
+    check_session_range(req.started_at, ended_at, state.server.now())?;
+    let timer = req
+        .timer
+        .as_deref()
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .unwrap_or(DEFAULT_TIMER);
+    if timer.chars().count() > MAX_TIMER_NAME_LEN {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({
+                "error": format!("Timer names can be at most {MAX_TIMER_NAME_LEN} characters")
+            })),
+        ));
+    }
+    match db::events::insert_manual_session(
+        &state.pool,
+        timer,
+        req.mode,
+        req.task.trim(),
+        req.started_at,
+        duration,
+    )
+    .await
+    {
+        Ok(id) => {
+            state.server.refresh_goal().await;
+            Ok(Json(json!({"session_id": id})))
+        }
+        Err(e) => {
+            log::error!("Create session failed: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "internal error"})),
+            ))
+        }
+    }
+}
+
+/// Corrects the start, end or task of a finished session
+async fn update_session_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateSessionRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let internal_error = |e: anyhow::Error| {
+        log::error!("Update session failed: {e}");
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({"error": "internal error"})),
+        )
+    };
+    let Some(session) = db::events::get_session(&state.pool, &id)
+        .await
+        .map_err(internal_error)?
+    else {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "session not found"})),
+        ));
+    };
+    if !matches!(
+        session.final_event.as_deref(),
+        Some("Completed" | "Terminated")
+    ) {
+        return Err((
+            StatusCode::CONFLICT,
+            Json(json!({"error": "session is still in progress"})),
+        ));
+    }
+    let parse = |at: &str| {
+        DateTime::parse_from_rfc3339(at)
+            .map(|at| at.with_timezone(&Local))
+            .map_err(|e| internal_error(e.into()))
+    };
+    let started_at = match req.started_at {
+        Some(at) => at,
+        None => parse(&session.started_at)?,
+    };
+    let ended_at = match req.ended_at {
+        Some(at) => at,
+        None => parse(&session.ended_at)?,
+    };
+    check_session_range(started_at, ended_at, state.server.now())?;
+    let task = req
+        .task
+        .as_deref()
+        .or(session.task.as_deref())
+        .unwrap_or_default()
+        .trim();
+
+    db::events::update_session(&state.pool, &id, started_at, ended_at, task)
+        .await
+        .map_err(internal_error)?;
+    state.server.refresh_goal().await;
+    match db::events::get_session(&state.pool, &id).await {
+        Ok(session) => Ok(Json(json!(session))),
+        Err(e) => Err(internal_error(e)),
+    }
+}
+
+async fn get_session_note_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match db::notes::get_session_note(&state.pool, &id).await {
+        Ok(Some(note)) => Ok(Json(serde_json::to_value(note).unwrap())),
+        Ok(None) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "no note for this session"})),
+        )),
+        Err(e) => {
+            log::error!("Session note query failed: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "internal error"})),
+            ))
+        }
+    }
+}
+
+async fn set_session_note_handler(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<SessionNoteRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let response = state
         .server
-        .process_request(crate::protocol::Request::SetPreset(req.preset))
+        .process_request(crate::protocol::Request::SetSessionNote {
+            session_id: id,
+            note: req.note,
+            rating: req.rating,
+        })
         .await;
-    Json(json!({"success": true}))
+    timer_reply(response)
 }
 
 #[derive(Deserialize)]
 struct HistoryQuery {
     limit: Option<u32>,
+    timer: Option<String>,
 }
 
 async fn get_history_handler(
@@ -159,7 +641,7 @@ async fn get_history_handler(
     Query(params): Query<HistoryQuery>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     let limit = params.limit.unwrap_or(20);
-    match db::events::get_sessions(&state.pool, limit).await {
+    match db::events::get_sessions(&state.pool, limit, params.timer.as_deref()).await {
         Ok(sessions) => Ok(Json(serde_json::to_value(sessions).unwrap())),
         Err(e) => {
             log::error!("History query failed: {e}");
@@ -171,6 +653,128 @@ async fn get_history_handler(
     }
 }
 
+// --- Preset endpoints ---
+
+#[derive(Deserialize)]
+struct CreatePresetRequest {
+    name: String,
+    work_secs: u64,
+    break_secs: u64,
+    long_break_secs: Option<u64>,
+}
+
+async fn get_presets_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match db::presets::get_all_presets(&state.pool).await {
+        Ok(rows) => {
+            let builtin = Preset::BUILTIN.iter().filter_map(|p| {
+                Durations::for_preset(p).map(|d| {
+                    json!({
+                        "name": p.to_string(),
+                        "work_secs": d.work.as_secs(),
+                        "break_secs": d.brk.as_secs(),
+                        "long_break_secs": d.long_brk.as_secs(),
+                        "builtin": true,
+                    })
+                })
+            });
+            let custom = rows.into_iter().map(|r| {
+                json!({
+                    "name": r.name,
+                    "work_secs": r.work_secs,
+                    "break_secs": r.break_secs,
+                    "long_break_secs": r.durations().long_brk.as_secs(),
+                    "builtin": false,
+                })
+            });
+            Ok(Json(json!(builtin.chain(custom).collect::<Vec<Value>>())))
+        }
+        Err(e) => {
+            log::error!("Get presets failed: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "internal error"})),
+            ))
+        }
+    }
+}
+
+/// Longest work or break time a preset can have
+const MAX_PRESET_SECS: u64 = 24 * 60 * 60;
+
+async fn create_preset_handler(
+    State(state): State<AppState>,
+    Json(req): Json<CreatePresetRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let name = req.name.trim();
+    if name.is_empty() || Preset::from(name.to_string()).is_builtin() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "name must be non-empty and not a built-in preset"})),
+        ));
+    }
+    if req.work_secs == 0 || req.break_secs == 0 || req.long_break_secs == Some(0) {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "durations must be positive"})),
+        ));
+    }
+    let durations = [
+        Some(req.work_secs),
+        Some(req.break_secs),
+        req.long_break_secs,
+    ];
+    if durations
+        .into_iter()
+        .flatten()
+        .any(|secs| secs > MAX_PRESET_SECS)
+    {
+        return Err((
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({"error": "durations must be at most a day"})),
+        ));
+    }
+    match db::presets::upsert_preset(
+        &state.pool,
+        name,
+        req.work_secs,
+        req.break_secs,
+        req.long_break_secs,
+    )
+    .await
+    {
+        Ok(()) => Ok(Json(json!({"name": name}))),
+        Err(e) => {
+            log::error!("Create preset failed: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "internal error"})),
+            ))
+        }
+    }
+}
+
+async fn delete_preset_handler(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match db::presets::delete_preset(&state.pool, &name).await {
+        Ok(true) => Ok(Json(json!({"success": true}))),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "preset not found"})),
+        )),
+        Err(e) => {
+            log::error!("Delete preset failed: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "internal error"})),
+            ))
+        }
+    }
+}
+
 // --- Dashboard ---
 
 async fn dashboard_handler() -> Html<&'static str> {
@@ -331,7 +935,8 @@ async fn get_daily_stats_handler(
     Query(params): Query<DailyStatsQuery>,
 ) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
     let days = params.days.unwrap_or(30);
-    match db::todos::get_daily_stats(&state.pool, days).await {
+    let today = state.server.now().date_naive();
+    match db::todos::get_daily_stats(&state.pool, today, days).await {
         Ok(stats) => Ok(Json(serde_json::to_value(stats).unwrap())),
         Err(e) => {
             log::error!("Daily stats failed: {e}");
@@ -343,32 +948,270 @@ async fn get_daily_stats_handler(
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::timer::{LogEvent, TimerMode};
-    use axum::{body::Body, http::Request};
-    use chrono::Local;
-    use sqlx::pool::PoolOptions;
-    use tower::ServiceExt;
-    use uuid::Uuid;
-
-    async fn test_app() -> (Router, SqlitePool) {
-        let pool = PoolOptions::<sqlx::Sqlite>::new()
-            .max_connections(1)
-            .connect("sqlite::memory:")
-            .await
-            .unwrap();
-        sqlx::migrate!("./migrations").run(&pool).await.unwrap();
-        let server = Arc::new(PomoServer::new(pool.clone()));
-        let state = AppState {
-            server,
-            pool: pool.clone(),
-        };
-        let app = Router::new()
-            .route("/timer/history", get(get_history_handler))
-            .with_state(state);
-        (app, pool)
+async fn get_break_stats_handler(
+    State(state): State<AppState>,
+    Query(params): Query<DailyStatsQuery>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let days = params.days.unwrap_or(30);
+    let today = state.server.now().date_naive();
+    match db::todos::get_break_stats(&state.pool, today, days).await {
+        Ok(stats) => Ok(Json(serde_json::to_value(stats).unwrap())),
+        Err(e) => {
+            log::error!("Break stats failed: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "internal error"})),
+            ))
+        }
+    }
+}
+
+async fn get_goal_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    let goal = db::goals::get_goal(&state.pool).await;
+    let progress = db::goals::get_progress(&state.pool, state.server.now().date_naive()).await;
+    match (goal, progress) {
+        (Ok(goal), Ok(progress)) => Ok(Json(json!({"goal": goal, "progress": progress}))),
+        (Err(e), _) | (_, Err(e)) => {
+            log::error!("Goal query failed: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "internal error"})),
+            ))
+        }
+    }
+}
+
+async fn set_goal_handler(
+    State(state): State<AppState>,
+    Json(goal): Json<db::goals::DailyGoal>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    if goal.target == 0 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "target must be positive"})),
+        ));
+    }
+    match db::goals::set_goal(&state.pool, &goal).await {
+        Ok(()) => {
+            state.server.refresh_goal().await;
+            Ok(Json(json!({"success": true})))
+        }
+        Err(e) => {
+            log::error!("Set goal failed: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "internal error"})),
+            ))
+        }
+    }
+}
+
+async fn delete_goal_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match db::goals::clear_goal(&state.pool).await {
+        Ok(true) => {
+            state.server.refresh_goal().await;
+            Ok(Json(json!({"success": true})))
+        }
+        Ok(false) => Err((StatusCode::NOT_FOUND, Json(json!({"error": "no goal set"})))),
+        Err(e) => {
+            log::error!("Delete goal failed: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "internal error"})),
+            ))
+        }
+    }
+}
+
+// --- Schedule endpoints ---
+
+#[derive(Deserialize)]
+struct CreateScheduleRequest {
+    weekdays: Vec<Weekday>,
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+#[derive(Deserialize)]
+struct UpdateScheduleRequest {
+    weekday: Weekday,
+    start: NaiveTime,
+    end: NaiveTime,
+}
+
+fn check_block_times(start: NaiveTime, end: NaiveTime) -> Result<(), (StatusCode, Json<Value>)> {
+    if start >= end {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "end must be after start"})),
+        ));
+    }
+    Ok(())
+}
+
+async fn get_schedule_handler(
+    State(state): State<AppState>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match db::schedule::get_blocks(&state.pool).await {
+        Ok(blocks) => Ok(Json(json!(blocks))),
+        Err(e) => {
+            log::error!("Get schedule failed: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "internal error"})),
+            ))
+        }
+    }
+}
+
+/// Creates one block per listed weekday with the same hours
+async fn create_schedule_handler(
+    State(state): State<AppState>,
+    Json(req): Json<CreateScheduleRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    check_block_times(req.start, req.end)?;
+    if req.weekdays.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({"error": "weekdays must not be empty"})),
+        ));
+    }
+    let mut blocks = Vec::with_capacity(req.weekdays.len());
+    for weekday in req.weekdays {
+        match db::schedule::create_block(&state.pool, weekday, req.start, req.end).await {
+            Ok(block) => blocks.push(block),
+            Err(e) => {
+                log::error!("Create schedule block failed: {e}");
+                state.server.reload_schedule().await;
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({"error": "internal error"})),
+                ));
+            }
+        }
+    }
+    state.server.reload_schedule().await;
+    Ok(Json(json!(blocks)))
+}
+
+async fn update_schedule_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(req): Json<UpdateScheduleRequest>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    check_block_times(req.start, req.end)?;
+    match db::schedule::update_block(&state.pool, id, req.weekday, req.start, req.end).await {
+        Ok(true) => {
+            state.server.reload_schedule().await;
+            Ok(Json(json!({"success": true})))
+        }
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "schedule block not found"})),
+        )),
+        Err(e) => {
+            log::error!("Update schedule block failed: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "internal error"})),
+            ))
+        }
+    }
+}
+
+async fn delete_schedule_handler(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Value>, (StatusCode, Json<Value>)> {
+    match db::schedule::delete_block(&state.pool, id).await {
+        Ok(true) => {
+            state.server.reload_schedule().await;
+            Ok(Json(json!({"success": true})))
+        }
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({"error": "schedule block not found"})),
+        )),
+        Err(e) => {
+            log::error!("Delete schedule block failed: {e}");
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({"error": "internal error"})),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timer::{DEFAULT_TIMER, LogEvent, TimerMode};
+    use axum::{body::Body, http::Request};
+    use chrono::Local;
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    async fn test_app() -> (Router, SqlitePool) {
+        let pool = crate::db::test_pool().await;
+        let server = Arc::new(PomoServer::new(
+            pool.clone(),
+            Arc::new(crate::clock::SystemClock),
+            Arc::new(crate::notify::Notifications::none()),
+            None,
+        ));
+        let state = AppState {
+            server,
+            pool: pool.clone(),
+        };
+        let app = router(state);
+        (app, pool)
+    }
+
+    #[tokio::test]
+    async fn test_transition_and_error_codes() {
+        let (app, _pool) = test_app().await;
+        let start = || {
+            Request::builder()
+                .method("POST")
+                .uri("/timer/start")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(start()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["from"]["run"], "Idle");
+        assert_eq!(body["to"]["run"], "Running");
+        assert_eq!(body["to"]["mode"], "Work");
+
+        let response = app.clone().oneshot(start()).await.unwrap();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["code"], "AlreadyRunning");
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/timer/cycle")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"length": 0}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
 
     #[tokio::test]
@@ -393,49 +1236,462 @@ mod tests {
 
     #[tokio::test]
     async fn test_history_returns_session() {
+        let (app, pool) = test_app().await;
+        crate::db::insert_completed_session(&pool, TimerMode::Work, "test", 1500).await;
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/timer/history?limit=5")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let sessions: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0]["timer_type"], "Work");
+        assert_eq!(sessions[0]["task"], "test");
+        assert_eq!(sessions[0]["final_event"], "Completed");
+        assert!(sessions[0]["note"].is_null());
+    }
+
+    #[tokio::test]
+    async fn test_set_session_note() {
         let (app, pool) = test_app().await;
         let id = Uuid::new_v4();
         crate::db::events::insert_event(
             &pool,
-            &LogEvent::Started {
-                id,
-                timer_type: TimerMode::Work,
-                task: "test".into(),
-                at: Local::now(),
-                remaining: 1500,
-            },
-        )
-        .await
-        .unwrap();
-        crate::db::events::insert_event(
-            &pool,
+            DEFAULT_TIMER,
             &LogEvent::Completed {
                 id,
                 task: "test".into(),
                 at: Local::now(),
-                work_secs: 1500,
+                active_secs: 1500,
+                planned_secs: 1500,
+                paused_secs: 0,
             },
         )
         .await
         .unwrap();
+        let put_note = |session_id: String, body: &'static str| {
+            Request::builder()
+                .method("PUT")
+                .uri(format!("/sessions/{session_id}/note"))
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(put_note(id.to_string(), r#"{"rating": 9}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let response = app
+            .clone()
+            .oneshot(put_note(Uuid::new_v4().to_string(), r#"{"rating": 3}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = app
+            .clone()
+            .oneshot(put_note(
+                id.to_string(),
+                r#"{"note": "fixed the flaky test", "rating": 4}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
 
+        let note = crate::db::notes::get_session_note(&pool, &id.to_string())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(note.note.as_deref(), Some("fixed the flaky test"));
+        assert_eq!(note.rating, Some(4));
+    }
+
+    #[tokio::test]
+    async fn test_create_and_select_preset() {
+        let (app, _pool) = test_app().await;
         let response = app
+            .clone()
             .oneshot(
                 Request::builder()
-                    .uri("/timer/history?limit=5")
+                    .method("POST")
+                    .uri("/presets")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        r#"{"name": "90/20", "work_secs": 5400, "break_secs": 1200}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/presets")
                     .body(Body::empty())
                     .unwrap(),
             )
             .await
             .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let presets: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(presets.len(), 4);
+        assert_eq!(presets[3]["name"], "90/20");
+        assert_eq!(presets[3]["builtin"], false);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/timer/preset")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"preset": "90/20"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
         assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_preset_rejects_builtin_name_and_unknown() {
+        let (app, _pool) = test_app().await;
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/presets")
+                    .header("content-type", "application/json")
+                    .body(Body::from(
+                        r#"{"name": "Long", "work_secs": 60, "break_secs": 60}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        for body in [
+            r#"{"name": "marathon", "work_secs": 86401, "break_secs": 60}"#,
+            r#"{"name": "marathon", "work_secs": 60, "break_secs": 60, "long_break_secs": 18446744073709551615}"#,
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("POST")
+                        .uri("/presets")
+                        .header("content-type", "application/json")
+                        .body(Body::from(body))
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        }
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/timer/preset")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"preset": "missing"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_goal_endpoints() {
+        let (app, _pool) = test_app().await;
+        let request = |method: &str, body: &'static str| {
+            Request::builder()
+                .method(method)
+                .uri("/goals")
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(request("PUT", r#"{"kind": "sessions", "target": 0}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let response = app
+            .clone()
+            .oneshot(request("PUT", r#"{"kind": "focus_secs", "target": 14400}"#))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app.clone().oneshot(request("GET", "")).await.unwrap();
         let body = axum::body::to_bytes(response.into_body(), usize::MAX)
             .await
             .unwrap();
-        let sessions: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
-        assert_eq!(sessions.len(), 1);
-        assert_eq!(sessions[0]["timer_type"], "Work");
-        assert_eq!(sessions[0]["task"], "test");
-        assert_eq!(sessions[0]["final_event"], "Completed");
+        let goal: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(goal["goal"]["kind"], "focus_secs");
+        assert_eq!(goal["progress"]["done"], 0);
+        assert_eq!(goal["progress"]["reached"], false);
+
+        let response = app.clone().oneshot(request("DELETE", "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.oneshot(request("DELETE", "")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_schedule_endpoints() {
+        let (app, _pool) = test_app().await;
+        let send = |method: &str, uri: &str, body: &'static str| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+
+        let response = app
+            .clone()
+            .oneshot(send(
+                "POST",
+                "/schedule",
+                r#"{"weekdays": ["Mon", "Tue"], "start": "09:00", "end": "12:00"}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let created: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(created.len(), 2);
+        let id = created[1]["id"].as_i64().unwrap();
+
+        let response = app
+            .clone()
+            .oneshot(send(
+                "POST",
+                "/schedule",
+                r#"{"weekdays": ["Wed"], "start": "12:00", "end": "09:00"}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .clone()
+            .oneshot(send(
+                "PUT",
+                &format!("/schedule/{id}"),
+                r#"{"weekday": "Fri", "start": "13:00", "end": "17:30"}"#,
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response = app
+            .clone()
+            .oneshot(send("DELETE", "/schedule/999", ""))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app.oneshot(send("GET", "/schedule", "")).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let blocks: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1]["weekday"], "Fri");
+        assert_eq!(blocks[1]["end"], "17:30:00");
+    }
+
+    #[tokio::test]
+    async fn test_timer_query_selects_named_timer() {
+        let (app, _pool) = test_app().await;
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/timer/task?timer=alice")
+                    .header("content-type", "application/json")
+                    .body(Body::from(r#"{"task": "report"}"#))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = app
+            .clone()
+            .oneshot(get("/timer/status?timer=alice"))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let status: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status["Status"]["task"], "report");
+
+        let response = app.clone().oneshot(get("/timer/status")).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let status: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(status["Status"]["task"], "");
+
+        let response = app
+            .clone()
+            .oneshot(get("/timer/status?timer=bob"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app.clone().oneshot(get("/timers")).await.unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let names: Vec<String> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(names, ["alice", "default"]);
+
+        let delete = |uri: &str| {
+            Request::builder()
+                .method("DELETE")
+                .uri(uri)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = app.clone().oneshot(delete("/timers/alice")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = app.clone().oneshot(delete("/timers/alice")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = app.oneshot(delete("/timers/default")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[tokio::test]
+    async fn test_create_and_update_session() {
+        let (app, _pool) = test_app().await;
+        let send = |method: &str, uri: &str, body: String| {
+            Request::builder()
+                .method(method)
+                .uri(uri)
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let start = Local::now() - chrono::Duration::hours(2);
+        let response = app
+            .clone()
+            .oneshot(send(
+                "POST",
+                "/sessions",
+                json!({"started_at": start, "duration_secs": 1500, "task": "review"}).to_string(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let created: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let id = created["session_id"].as_str().unwrap().to_string();
+
+        let future = json!({"started_at": Local::now(), "duration_secs": 60}).to_string();
+        let response = app
+            .clone()
+            .oneshot(send("POST", "/sessions", future))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let long_timer = json!({"started_at": start, "duration_secs": 60, "timer": "x".repeat(65)});
+        let response = app
+            .clone()
+            .oneshot(send("POST", "/sessions", long_timer.to_string()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        for duration_secs in [24 * 60 * 60 + 1, u64::MAX] {
+            let too_long = json!({"started_at": start, "duration_secs": duration_secs});
+            let response = app
+                .clone()
+                .oneshot(send("POST", "/sessions", too_long.to_string()))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        }
+
+        let response = app
+            .clone()
+            .oneshot(send(
+                "PATCH",
+                &format!("/sessions/{id}"),
+                json!({"ended_at": start + chrono::Duration::minutes(50), "task": "code review"})
+                    .to_string(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let session: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(session["task"], "code review");
+        assert_eq!(session["work_secs"], 3000);
+        assert_eq!(session["manual"], true);
+
+        let response = app
+            .clone()
+            .oneshot(send(
+                "PATCH",
+                &format!("/sessions/{id}"),
+                json!({"ended_at": start - chrono::Duration::minutes(1)}).to_string(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+        let response = app
+            .clone()
+            .oneshot(send(
+                "PATCH",
+                &format!("/sessions/{id}"),
+                json!({"started_at": start - chrono::Duration::days(2)}).to_string(),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let response = app
+            .oneshot(send("PATCH", "/sessions/nope", "{}".into()))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
     }
 }
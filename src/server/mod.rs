@@ -1,3 +1,5 @@
 pub mod core;
 pub mod http;
 pub mod tcp;
+#[cfg(unix)]
+pub mod unix;
@@ -1,14 +1,27 @@
 // Copyright (c) 2025 Yu-Wen Chen
 // Licensed under the MIT License (see LICENSE file)
 
+use crate::protocol::messages::{
+    CAPABILITIES, ErrorCode, PROTOCOL_VERSION, Push, PushEnvelope, RequestEnvelope,
+    ResponseEnvelope,
+};
 use crate::protocol::{Request, Response};
+use crate::timer::DEFAULT_TIMER;
 use anyhow::Result;
+use serde_json::Value;
 use std::sync::Arc;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::net::{TcpListener, TcpStream};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
 
 use crate::server::core::PomoServer;
 
+/// Lines a connection can have queued before the server waits for the client
+/// to read them
+const LINE_BUFFER: usize = 64;
+
 pub struct TcpServer {
     server: Arc<PomoServer>,
 }
@@ -29,8 +42,10 @@ impl TcpServer {
             eprintln!("New client connected");
 
             let server = Arc::clone(&self.server);
+            let (read_half, write_half) = stream.into_split();
             tokio::spawn(async move {
-                if let Err(e) = Self::handle_connection_static(server, stream).await {
+                if let Err(e) = Self::handle_connection_static(server, read_half, write_half).await
+                {
                     eprintln!("Error handling connection: {}", e);
                 }
             });
@@ -40,12 +55,39 @@ impl TcpServer {
         // Ok(())
     }
 
-    async fn handle_connection_static(server: Arc<PomoServer>, stream: TcpStream) -> Result<()> {
-        let (read_half, mut write_half) = stream.into_split();
+    /// Serves newline-delimited JSON requests on a connection until it
+    /// closes. Shared by every transport.
+    pub(crate) async fn handle_connection_static<R, W>(
+        server: Arc<PomoServer>,
+        read_half: R,
+        mut write_half: W,
+    ) -> Result<()>
+    where
+        R: AsyncRead + Unpin,
+        W: AsyncWrite + Unpin + Send + 'static,
+    {
         // create BufReader for line-based reading
         let mut reader = BufReader::new(read_half);
         let mut line = String::new();
 
+        // Every line to the client goes through one writer task, so responses
+        // and pushes never interleave with each other
+        let (lines_tx, mut lines_rx) = mpsc::channel::<String>(LINE_BUFFER);
+        let writer = tokio::spawn(async move {
+            while let Some(line) = lines_rx.recv().await {
+                write_half.write_all(line.as_bytes()).await?;
+                write_half.write_all(b"\n").await?;
+            }
+            anyhow::Ok(())
+        });
+
+        let mut connection = Connection {
+            version: 1,
+            capabilities: Vec::new(),
+            lines: lines_tx,
+            subscription: None,
+        };
+
         loop {
             line.clear();
             // Read JSON requests line by line
@@ -55,25 +97,10 @@ impl TcpServer {
                     break;
                 }
                 Ok(_) => {
-                    let request_str = line.trim();
-
-                    // Deserialize to Request
-                    match serde_json::from_str::<Request>(request_str) {
-                        Ok(request) => {
-                            let response = server.process_request(request).await;
-
-                            // Send JSON response back
-                            let response_json = serde_json::to_string(&response)?;
-                            write_half.write_all(response_json.as_bytes()).await?;
-                            write_half.write_all(b"\n").await?;
-                        }
-                        Err(e) => {
-                            eprintln!("Invalid JSON request: {}", e);
-                            let error_response = Response::Error(format!("Invalid JSON: {}", e));
-                            let error_json = serde_json::to_string(&error_response)?;
-                            write_half.write_all(error_json.as_bytes()).await?;
-                            write_half.write_all(b"\n").await?;
-                        }
+                    let response = connection.answer(&server, line.trim()).await?;
+                    if connection.lines.send(response).await.is_err() {
+                        // The writer failed; its error is reported below
+                        break;
                     }
                 }
                 Err(e) => {
@@ -82,6 +109,342 @@ impl TcpServer {
                 }
             }
         }
-        Ok(())
+        if let Some(subscription) = connection.subscription.take() {
+            subscription.abort();
+        }
+        drop(connection);
+        writer.await?
+    }
+
+    /// Sends the pushes for `timer` to the connection until it closes
+    async fn forward_pushes(
+        server: Arc<PomoServer>,
+        mut pushes: tokio::sync::broadcast::Receiver<Push>,
+        timer: String,
+        lines: mpsc::Sender<String>,
+    ) {
+        loop {
+            let push = match pushes.recv().await {
+                Ok(push) if push.timer() == timer => push,
+                Ok(_) => continue,
+                // Catch the client up with a fresh status instead
+                Err(RecvError::Lagged(missed)) => {
+                    eprintln!("Subscriber fell behind by {} pushes", missed);
+                    let request = Request::Subscribe.for_timer(Some(&timer));
+                    let Response::Status(status) = server.process_request(request).await else {
+                        continue;
+                    };
+                    Push::Status {
+                        timer: timer.clone(),
+                        status,
+                    }
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let line = match serde_json::to_string(&PushEnvelope { push }) {
+                Ok(line) => line,
+                Err(e) => {
+                    eprintln!("Failed to serialize push: {}", e);
+                    continue;
+                }
+            };
+            // A client that stops reading holds this task up, so it misses
+            // pushes and gets a fresh status once it catches up
+            if lines.send(line).await.is_err() {
+                break;
+            }
+        }
+    }
+}
+
+/// What a connection agreed on in its `Hello`, and where its lines go.
+/// Clients from before the handshake never say `Hello`, so a connection
+/// speaks version 1 without capabilities until it does.
+struct Connection {
+    /// Protocol version both sides speak
+    version: u32,
+    /// Capabilities both sides support
+    capabilities: Vec<String>,
+    lines: mpsc::Sender<String>,
+    /// Forwards pushes once the client subscribed
+    subscription: Option<JoinHandle<()>>,
+}
+
+impl Connection {
+    fn supports(&self, capability: &str) -> bool {
+        self.capabilities.iter().any(|c| c == capability)
+    }
+
+    /// Processes one request line and returns the JSON response. A request in
+    /// a `RequestEnvelope` gets its id echoed in a `ResponseEnvelope`; a bare
+    /// request gets a bare response, as before envelopes existed.
+    async fn answer(&mut self, server: &Arc<PomoServer>, line: &str) -> Result<String> {
+        if let Ok(envelope) = serde_json::from_str::<RequestEnvelope<Value>>(line) {
+            let response = match serde_json::from_value::<Request>(envelope.request) {
+                Ok(request) => self.process(server, request).await,
+                Err(e) => {
+                    eprintln!("Invalid JSON request: {}", e);
+                    Response::error(ErrorCode::InvalidRequest, format!("Invalid JSON: {}", e))
+                }
+            };
+            let envelope = ResponseEnvelope {
+                id: envelope.id,
+                response: self.encode(response)?,
+            };
+            return Ok(serde_json::to_string(&envelope)?);
+        }
+
+        // Deserialize to Request
+        let response = match serde_json::from_str::<Request>(line) {
+            Ok(request) => self.process(server, request).await,
+            Err(e) => {
+                eprintln!("Invalid JSON request: {}", e);
+                Response::error(ErrorCode::InvalidRequest, format!("Invalid JSON: {}", e))
+            }
+        };
+        Ok(serde_json::to_string(&self.encode(response)?)?)
+    }
+
+    /// The response as the connection's protocol version reads it. Version 1
+    /// had no transitions and sent errors as a plain message.
+    fn encode(&self, response: Response) -> Result<Value> {
+        if self.version >= 2 {
+            return Ok(serde_json::to_value(response)?);
+        }
+        Ok(match response {
+            Response::Transition { .. } => serde_json::to_value(Response::Ok)?,
+            Response::Error(e) => serde_json::json!({ "Error": e.message }),
+            other => serde_json::to_value(other)?,
+        })
+    }
+
+    /// Processes a request. A `Hello` records the agreed version and
+    /// capabilities; a successful `Subscribe` starts forwarding pushes to the
+    /// connection, replacing any earlier subscription.
+    async fn process(&mut self, server: &Arc<PomoServer>, request: Request) -> Response {
+        let timer = match &request {
+            Request::Subscribe => Some(DEFAULT_TIMER.to_string()),
+            Request::ForTimer { timer, request } if matches!(**request, Request::Subscribe) => {
+                Some(timer.trim().to_string())
+            }
+            _ => None,
+        };
+        if timer.is_some() && !self.supports("subscribe") {
+            return Response::error(
+                ErrorCode::InvalidRequest,
+                "Subscribe needs the subscribe capability in Hello",
+            );
+        }
+        // Subscribe before taking the status so no change falls in between
+        let pushes = timer.as_ref().map(|_| server.subscribe());
+
+        let hello = match &request {
+            Request::Hello {
+                version,
+                capabilities,
+            } => Some((*version, capabilities.clone())),
+            _ => None,
+        };
+        let response = server.process_request(request).await;
+        if let (Some(timer), Some(pushes), Response::Status(_)) = (timer, pushes, &response) {
+            if let Some(previous) = self.subscription.take() {
+                previous.abort();
+            }
+            self.subscription = Some(tokio::spawn(TcpServer::forward_pushes(
+                Arc::clone(server),
+                pushes,
+                timer,
+                self.lines.clone(),
+            )));
+        }
+        if let (Some((version, capabilities)), Response::Hello { .. }) = (hello, &response) {
+            self.version = version.min(PROTOCOL_VERSION);
+            self.capabilities = capabilities
+                .into_iter()
+                .filter(|c| CAPABILITIES.contains(&c.as_str()))
+                .collect();
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tcp::PomoClient;
+    use tokio::net::TcpStream;
+
+    /// Serves one connection on a local port and returns its address
+    async fn serve_one() -> String {
+        let pool = crate::db::test_pool().await;
+        let server = Arc::new(PomoServer::new(
+            pool,
+            Arc::new(crate::clock::SystemClock),
+            Arc::new(crate::notify::Notifications::none()),
+            None,
+        ));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read_half, write_half) = stream.into_split();
+            TcpServer::handle_connection_static(server, read_half, write_half)
+                .await
+                .unwrap();
+        });
+        addr
+    }
+
+    #[tokio::test]
+    async fn test_pipelined_requests() {
+        let mut client = PomoClient::new();
+        client.connect(&serve_one().await).await.unwrap();
+
+        let (pong, status, start) = tokio::join!(
+            client.send_request(Request::Ping),
+            client.get_status(),
+            client.send_request(Request::Start),
+        );
+        assert!(matches!(pong.unwrap(), Response::Pong));
+        assert!(matches!(status.unwrap(), Response::Status(_)));
+        assert!(matches!(start.unwrap(), Response::Transition { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_envelope_ids_are_echoed() {
+        let stream = TcpStream::connect(serve_one().await).await.unwrap();
+        let (read_half, mut write_half) = stream.into_split();
+        let mut lines = BufReader::new(read_half).lines();
+
+        write_half
+            .write_all(
+                b"{\"Hello\":{\"version\":2,\"capabilities\":[]}}\n\
+                  {\"id\":7,\"request\":\"Ping\"}\n{\"id\":8,\"request\":\"Nope\"}\n\"Ping\"\n",
+            )
+            .await
+            .unwrap();
+        lines.next_line().await.unwrap().unwrap();
+
+        let line = lines.next_line().await.unwrap().unwrap();
+        let envelope: ResponseEnvelope = serde_json::from_str(&line).unwrap();
+        assert_eq!(envelope.id, 7);
+        assert!(matches!(envelope.response, Response::Pong));
+
+        let line = lines.next_line().await.unwrap().unwrap();
+        let envelope: ResponseEnvelope = serde_json::from_str(&line).unwrap();
+        assert_eq!(envelope.id, 8);
+        assert!(
+            matches!(envelope.response, Response::Error(e) if e.code == ErrorCode::InvalidRequest)
+        );
+
+        // Requests without an envelope get a bare response
+        let line = lines.next_line().await.unwrap().unwrap();
+        assert_eq!(line, "\"Pong\"");
+    }
+
+    async fn next_push(pushes: &mut mpsc::Receiver<Push>) -> Push {
+        tokio::time::timeout(std::time::Duration::from_secs(3), pushes.recv())
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_pushes_changes() {
+        let mut client = PomoClient::new();
+        client.connect(&serve_one().await).await.unwrap();
+        let (status, mut pushes) = client.subscribe().await.unwrap();
+        assert!(status.is_idle);
+
+        client.send_request(Request::Start).await.unwrap();
+        let Push::Status { timer, status } = next_push(&mut pushes).await else {
+            panic!("expected a status push");
+        };
+        assert_eq!(timer, DEFAULT_TIMER);
+        assert!(status.is_running);
+
+        // The event follows once the tick loop stored it
+        let Push::Event { event, .. } = next_push(&mut pushes).await else {
+            panic!("expected an event push");
+        };
+        assert!(matches!(event, crate::timer::LogEvent::Started { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_needs_capability() {
+        let stream = TcpStream::connect(serve_one().await).await.unwrap();
+        let (read_half, mut write_half) = stream.into_split();
+        let mut lines = BufReader::new(read_half).lines();
+
+        write_half
+            .write_all(b"{\"Hello\":{\"version\":2,\"capabilities\":[\"undo\"]}}\n\"Subscribe\"\n")
+            .await
+            .unwrap();
+        let line = lines.next_line().await.unwrap().unwrap();
+        assert!(matches!(
+            serde_json::from_str(&line).unwrap(),
+            Response::Hello { version: 2, .. }
+        ));
+        let line = lines.next_line().await.unwrap().unwrap();
+        assert!(matches!(
+            serde_json::from_str(&line).unwrap(),
+            Response::Error(e) if e.code == ErrorCode::InvalidRequest
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_version_1_responses() {
+        let stream = TcpStream::connect(serve_one().await).await.unwrap();
+        let (read_half, mut write_half) = stream.into_split();
+        let mut lines = BufReader::new(read_half).lines();
+
+        write_half
+            .write_all(
+                b"{\"Hello\":{\"version\":1,\"capabilities\":[]}}\n\"Start\"\n\"Pause\"\n\"Pause\"\n",
+            )
+            .await
+            .unwrap();
+        let line = lines.next_line().await.unwrap().unwrap();
+        assert!(matches!(
+            serde_json::from_str(&line).unwrap(),
+            Response::Hello { version: 2, .. }
+        ));
+        // Transitions are plain `Ok` and errors a bare message
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "\"Ok\"");
+        assert_eq!(lines.next_line().await.unwrap().unwrap(), "\"Ok\"");
+        let line = lines.next_line().await.unwrap().unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert!(response["Error"].is_string());
+    }
+
+    /// `Response` as clients from before the handshake read it
+    #[derive(serde::Deserialize)]
+    enum LegacyResponse {
+        Ok,
+        Error(String),
+    }
+
+    #[tokio::test]
+    async fn test_no_hello_gets_version_1() {
+        let stream = TcpStream::connect(serve_one().await).await.unwrap();
+        let (read_half, mut write_half) = stream.into_split();
+        let mut lines = BufReader::new(read_half).lines();
+
+        write_half
+            .write_all(b"\"Start\"\n\"Start\"\n\"Subscribe\"\n")
+            .await
+            .unwrap();
+        let line = lines.next_line().await.unwrap().unwrap();
+        assert!(matches!(
+            serde_json::from_str(&line).unwrap(),
+            LegacyResponse::Ok
+        ));
+        for _ in 0..2 {
+            let line = lines.next_line().await.unwrap().unwrap();
+            assert!(matches!(
+                serde_json::from_str(&line).unwrap(),
+                LegacyResponse::Error(message) if !message.is_empty()
+            ));
+        }
     }
 }
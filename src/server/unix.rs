@@ -0,0 +1,163 @@
+// Copyright (c) 2025 Yu-Wen Chen
+// Licensed under the MIT License (see LICENSE file)
+
+use anyhow::Result;
+use std::fs::DirBuilder;
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::net::{UnixListener, UnixStream};
+
+use crate::server::core::PomoServer;
+use crate::server::tcp::TcpServer;
+
+/// Where the server listens for local clients: `$XDG_RUNTIME_DIR/pomo-tui.sock`,
+/// or next to the database when there is no runtime directory
+pub fn default_socket_path() -> Option<PathBuf> {
+    dirs::runtime_dir()
+        .map(|p| p.join("pomo-tui.sock"))
+        .or_else(|| dirs::data_local_dir().map(|p| p.join("pomo-tui/pomo.sock")))
+}
+
+/// Serves the TCP protocol on a Unix socket that only its owner can open
+pub struct UnixServer {
+    server: Arc<PomoServer>,
+}
+
+/// Removes the socket file when the server stops
+struct SocketFile(PathBuf);
+
+impl Drop for SocketFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+impl UnixServer {
+    pub fn new(server: Arc<PomoServer>) -> Self {
+        Self { server }
+    }
+
+    pub async fn start(&self, path: &Path) -> Result<()> {
+        if path.exists() {
+            if UnixStream::connect(path).await.is_ok() {
+                anyhow::bail!("Another server is listening on {}", path.display());
+            }
+            // Left behind by a server that didn't shut down cleanly
+            std::fs::remove_file(path)?;
+        }
+        let listener = bind_private(path)?;
+        let _socket = SocketFile(path.to_path_buf());
+        eprintln!("Pomo UnixServer listening on {}", path.display());
+
+        loop {
+            let (stream, _) = listener.accept().await?;
+            eprintln!("New local client connected");
+
+            let server = Arc::clone(&self.server);
+            let (read_half, write_half) = stream.into_split();
+            tokio::spawn(async move {
+                if let Err(e) =
+                    TcpServer::handle_connection_static(server, read_half, write_half).await
+                {
+                    eprintln!("Error handling connection: {}", e);
+                }
+            });
+        }
+    }
+}
+
+/// Binds `path` so that only its owner can ever connect. The socket is
+/// created in a fresh directory only the owner can enter, made private and
+/// then moved into place, so there is no moment where others could reach it.
+fn bind_private(path: &Path) -> Result<UnixListener> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    if !dir.as_os_str().is_empty() {
+        DirBuilder::new().recursive(true).mode(0o700).create(dir)?;
+    }
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid socket path {}", path.display()))?;
+    let staging = dir.join(format!(
+        ".{}.{}",
+        name.to_string_lossy(),
+        std::process::id()
+    ));
+    // Left behind by an earlier run that had the same process id
+    let _ = std::fs::remove_dir_all(&staging);
+    DirBuilder::new().mode(0o700).create(&staging)?;
+    let staged = staging.join(name);
+    let bound = UnixListener::bind(&staged).and_then(|listener| {
+        std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+        std::fs::rename(&staged, path)?;
+        Ok(listener)
+    });
+    let _ = std::fs::remove_file(&staged);
+    std::fs::remove_dir(&staging)?;
+    Ok(bound?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::tcp::PomoClient;
+    use crate::protocol::{Request, Response};
+
+    async fn test_server() -> Arc<PomoServer> {
+        let pool = crate::db::test_pool().await;
+        Arc::new(PomoServer::new(
+            pool,
+            Arc::new(crate::clock::SystemClock),
+            Arc::new(crate::notify::Notifications::none()),
+            None,
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_unix_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("pomo.sock");
+        // A stale socket from a crashed server is replaced
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        let unix_server = UnixServer::new(test_server().await);
+        let server_path = path.clone();
+        let task = tokio::spawn(async move { unix_server.start(&server_path).await });
+        while UnixStream::connect(&path).await.is_err() {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+
+        let mut client = PomoClient::new();
+        client.connect_unix(&path).await.unwrap();
+        let response = client.send_request(Request::Ping).await.unwrap();
+        assert!(matches!(response, Response::Pong));
+
+        // A second server refuses to take over the socket
+        let second = UnixServer::new(test_server().await);
+        assert!(second.start(&path).await.is_err());
+
+        task.abort();
+        let _ = task.await;
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_socket_directory_is_private() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run/pomo.sock");
+        let listener = bind_private(&path).unwrap();
+        let mode = std::fs::metadata(path.parent().unwrap())
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o700);
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        // Only the socket is left behind
+        let entries = std::fs::read_dir(path.parent().unwrap()).unwrap().count();
+        assert_eq!(entries, 1);
+        drop(listener);
+    }
+}
@@ -1,23 +1,46 @@
 // Copyright (c) 2025 Yu-Wen Chen
 // Licensed under the MIT License (see LICENSE file)
 
+use crate::clock::{Clock, SystemClock};
+use crate::db::goals::GoalProgress;
+use crate::notify::{Notice, NoticeKind};
 use chrono::{DateTime, Local};
-use notify_rust::Notification;
 use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 use std::fmt;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 const MIN: u64 = 60;
 const MAX_EMIT_EVENTS: usize = 1000;
+/// Longest grace period before an auto-continued session starts
+pub const MAX_GRACE_PERIOD: Duration = Duration::from_secs(60 * MIN);
+/// Long breaks default to this multiple of the short break
+const LONG_BREAK_FACTOR: u32 = 3;
+pub const DEFAULT_CYCLE_LEN: u32 = 4;
+pub const DEFAULT_GRACE_SECS: u64 = 5;
+/// How long a reset, mode switch or preset change can be undone
+const UNDO_WINDOW: Duration = Duration::from_secs(30);
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+/// Built-in presets serialize as their bare name ("Short", "Long", "Test"); any
+/// other name refers to a user-defined preset stored in the `presets` table.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
 pub enum Preset {
     #[default]
     Short,
     Long,
     Test,
+    Custom(String),
+}
+
+impl Preset {
+    pub const BUILTIN: [Preset; 3] = [Preset::Short, Preset::Long, Preset::Test];
+
+    pub fn is_builtin(&self) -> bool {
+        !matches!(self, Preset::Custom(_))
+    }
 }
 
 impl fmt::Display for Preset {
@@ -26,11 +49,29 @@ impl fmt::Display for Preset {
             Preset::Short => f.write_str("Short"),
             Preset::Long => f.write_str("Long"),
             Preset::Test => f.write_str("Test"),
+            Preset::Custom(name) => f.write_str(name),
         }
     }
 }
 
-#[derive(Debug, Default, Clone, PartialEq, Serialize)]
+impl From<String> for Preset {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "Short" => Preset::Short,
+            "Long" => Preset::Long,
+            "Test" => Preset::Test,
+            _ => Preset::Custom(name),
+        }
+    }
+}
+
+impl From<Preset> for String {
+    fn from(p: Preset) -> Self {
+        p.to_string()
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
 pub enum LogEvent {
     #[default]
     Idle,
@@ -40,6 +81,9 @@ pub enum LogEvent {
         task: String,
         at: DateTime<Local>,
         remaining: u64,
+        /// Preset the session was started with; `None` for sessions logged by hand
+        #[serde(default)]
+        preset: Option<String>,
     },
     Paused {
         id: Uuid,
@@ -58,36 +102,253 @@ pub enum LogEvent {
         task: String,
         at: DateTime<Local>,
         remaining: u64,
-        work_secs: u64,
+        /// Seconds the session actually ran, pauses excluded
+        active_secs: u64,
+        /// Time the session had when it started, plus extensions
+        planned_secs: u64,
+        paused_secs: u64,
+        /// Why the session was abandoned; `None` when the user moved on to
+        /// another session
+        #[serde(default)]
+        reason: Option<TerminationReason>,
     },
     Completed {
         id: Uuid,
         task: String,
         at: DateTime<Local>,
-        work_secs: u64,
+        /// Seconds the session actually ran, overtime included and pauses excluded
+        active_secs: u64,
+        /// Time the session had when it started, plus extensions
+        planned_secs: u64,
+        paused_secs: u64,
+    },
+    Extended {
+        id: Uuid,
+        task: String,
+        at: DateTime<Local>,
+        added: u64,
+        remaining: u64,
+    },
+    Interrupted {
+        id: Uuid,
+        task: String,
+        at: DateTime<Local>,
+        kind: InterruptionKind,
+        note: Option<String>,
+        remaining: u64,
+    },
+    /// A work-hours block began and started (or kept) this session
+    ScheduleStarted {
+        id: Uuid,
+        task: String,
+        at: DateTime<Local>,
+        remaining: u64,
+    },
+    /// A work-hours block ended and turned auto-continue off
+    ScheduleStopped {
+        id: Uuid,
+        task: String,
+        at: DateTime<Local>,
+        remaining: u64,
     },
+    /// A break was switched away from before it started. The id belongs to no
+    /// session.
+    Skipped {
+        id: Uuid,
+        timer_type: TimerMode,
+        task: String,
+        at: DateTime<Local>,
+    },
+    /// An undo brought back the session (or skipped break) `id`, cancelling
+    /// its Terminated (or Skipped) event
+    Undone {
+        id: Uuid,
+        task: String,
+        at: DateTime<Local>,
+        remaining: u64,
+    },
+}
+
+impl LogEvent {
+    /// The variant's name, as stored in `events.event_type`
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Idle => "Idle",
+            Self::Started { .. } => "Started",
+            Self::Paused { .. } => "Paused",
+            Self::Resumed { .. } => "Resumed",
+            Self::Terminated { .. } => "Terminated",
+            Self::Completed { .. } => "Completed",
+            Self::Extended { .. } => "Extended",
+            Self::Interrupted { .. } => "Interrupted",
+            Self::ScheduleStarted { .. } => "ScheduleStarted",
+            Self::ScheduleStopped { .. } => "ScheduleStopped",
+            Self::Skipped { .. } => "Skipped",
+            Self::Undone { .. } => "Undone",
+        }
+    }
+
+    /// The session (or skipped break) the event belongs to; `None` for `Idle`
+    pub fn id(&self) -> Option<Uuid> {
+        self.common().map(|(id, _, _)| id)
+    }
+
+    pub fn task(&self) -> Option<&str> {
+        self.common().map(|(_, task, _)| task)
+    }
+
+    pub fn at(&self) -> Option<DateTime<Local>> {
+        self.common().map(|(_, _, at)| at)
+    }
+
+    /// The id, task and time every event but `Idle` carries
+    fn common(&self) -> Option<(Uuid, &str, DateTime<Local>)> {
+        match self {
+            Self::Idle => None,
+            Self::Started { id, task, at, .. }
+            | Self::Paused { id, task, at, .. }
+            | Self::Resumed { id, task, at, .. }
+            | Self::Terminated { id, task, at, .. }
+            | Self::Completed { id, task, at, .. }
+            | Self::Extended { id, task, at, .. }
+            | Self::Interrupted { id, task, at, .. }
+            | Self::ScheduleStarted { id, task, at, .. }
+            | Self::ScheduleStopped { id, task, at, .. }
+            | Self::Skipped { id, task, at, .. }
+            | Self::Undone { id, task, at, .. } => Some((*id, task.as_str(), *at)),
+        }
+    }
+}
+
+/// Pomodoro interruptions: internal ones come from yourself (a thought, an
+/// urge to check mail), external ones from someone or something else
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InterruptionKind {
+    Internal,
+    External,
+}
+
+impl fmt::Display for InterruptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterruptionKind::Internal => f.write_str("Internal"),
+            InterruptionKind::External => f.write_str("External"),
+        }
+    }
+}
+
+/// Why a session was abandoned rather than followed by another one
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TerminationReason {
+    /// It stayed paused longer than the maximum pause
+    MaxPause,
+    /// The user reset the timer
+    Reset,
+}
+
+impl TerminationReason {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::MaxPause => "max_pause",
+            Self::Reset => "reset",
+        }
+    }
+}
+
+/// State to go back to with `Timer::undo`
+#[derive(Debug, Clone)]
+struct Undo {
+    at: Instant,
+    session: SessionState,
+    /// Session whose Terminated or Skipped event the undo cancels
+    undone: Option<Uuid>,
+}
+
+/// The parts of a `Timer` an undo brings back. Settings such as auto-continue,
+/// flow mode, grace, cycle length, maximum pause and the pending preset are
+/// left out so that changes made in between survive the undo.
+#[derive(Debug, Clone)]
+struct SessionState {
+    started_at: Option<Instant>,
+    remaining: Duration,
+    mode: TimerMode,
+    /// The preset the session ran with, so undoing a preset change brings it back
+    timeset: Preset,
+    durs: Durations,
+    paused: bool,
+    idle: bool,
+    task_name: String,
+    id: Option<Uuid>,
+    cycle_pos: u32,
+    pending_start: Option<Instant>,
+    extended: Duration,
+    overtime: bool,
+    active: Duration,
+    planned: Duration,
+    paused_total: Duration,
+    interruptions: u32,
+    last_id: Option<Uuid>,
+    paused_at: Option<Instant>,
 }
 
-#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+/// How far a session got before the server stopped, for `Timer::restore`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionProgress {
+    /// Time the session had when it started, plus extensions
+    pub planned: Duration,
+    pub remaining: Duration,
+    pub paused: Duration,
+    /// Time the session already ran, pauses excluded
+    pub active: Duration,
+}
+
+/// Name of the timer used when a request doesn't name one
+pub const DEFAULT_TIMER: &str = "default";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum TimerMode {
     // NOTE: pub so that we can use it outside of timer.rs module
     #[default]
     Work,
     Break,
+    LongBreak,
+    /// Open-ended session that counts up from zero with no deadline
+    Stopwatch,
 }
 
 impl TimerMode {
     pub const fn toggle(self) -> Self {
         match self {
             Self::Work => Self::Break,
-            Self::Break => Self::Work,
+            Self::Break | Self::LongBreak | Self::Stopwatch => Self::Work,
         }
     }
 
+    pub const fn is_break(self) -> bool {
+        matches!(self, Self::Break | Self::LongBreak)
+    }
+
     fn duration(self, d: &Durations) -> Duration {
         match self {
             Self::Work => d.work,
             Self::Break => d.brk,
+            Self::LongBreak => d.long_brk,
+            Self::Stopwatch => Duration::ZERO,
+        }
+    }
+}
+
+impl std::str::FromStr for TimerMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Work" => Ok(TimerMode::Work),
+            "Break" => Ok(TimerMode::Break),
+            "LongBreak" => Ok(TimerMode::LongBreak),
+            "Stopwatch" => Ok(TimerMode::Stopwatch),
+            other => Err(format!("Unknown timer mode: {other}")),
         }
     }
 }
@@ -97,37 +358,47 @@ impl fmt::Display for TimerMode {
         match self {
             TimerMode::Work => f.write_str("Work"),
             TimerMode::Break => f.write_str("Break"),
+            TimerMode::LongBreak => f.write_str("LongBreak"),
+            TimerMode::Stopwatch => f.write_str("Stopwatch"),
         }
     }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
-struct Durations {
-    work: Duration,
-    brk: Duration,
+pub struct Durations {
+    pub work: Duration,
+    pub brk: Duration,
+    pub long_brk: Duration,
 }
 
 impl Durations {
-    pub const SHORT: Self = Self {
-        work: Duration::from_secs(25 * MIN),
-        brk: Duration::from_secs(5 * MIN),
-    };
+    /// The long break defaults to `LONG_BREAK_FACTOR` times the short break
+    pub const fn new(work: Duration, brk: Duration) -> Self {
+        Self {
+            work,
+            brk,
+            long_brk: brk.saturating_mul(LONG_BREAK_FACTOR),
+        }
+    }
 
-    pub const LONG: Self = Self {
-        work: Duration::from_secs(50 * MIN),
-        brk: Duration::from_secs(10 * MIN),
-    };
+    pub const fn with_long_break(self, long_brk: Duration) -> Self {
+        Self { long_brk, ..self }
+    }
 
-    pub const TEST: Self = Self {
-        work: Duration::from_secs(5),
-        brk: Duration::from_secs(5),
-    };
+    pub const SHORT: Self = Self::new(Duration::from_secs(25 * MIN), Duration::from_secs(5 * MIN));
+
+    pub const LONG: Self = Self::new(Duration::from_secs(50 * MIN), Duration::from_secs(10 * MIN));
 
-    pub const fn for_preset(p: Preset) -> Self {
+    pub const TEST: Self = Self::new(Duration::from_secs(5), Duration::from_secs(5));
+
+    /// Durations of a built-in preset. Custom presets live in the database,
+    /// so the caller has to resolve them and use `Timer::set_custom_preset`.
+    pub const fn for_preset(p: &Preset) -> Option<Self> {
         match p {
-            Preset::Short => Self::SHORT,
-            Preset::Long => Self::LONG,
-            Preset::Test => Self::TEST,
+            Preset::Short => Some(Self::SHORT),
+            Preset::Long => Some(Self::LONG),
+            Preset::Test => Some(Self::TEST),
+            Preset::Custom(_) => None,
         }
     }
 }
@@ -138,48 +409,174 @@ impl Default for Durations {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Whether a timer is counting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RunState {
+    Idle,
+    Running,
+    Paused,
+}
+
+/// Where a timer stands, as reported before and after a request changes it
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimerState {
+    pub mode: TimerMode,
+    pub run: RunState,
+}
+
+/// Fields missing from an older server's status take their default values
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TimerStatus {
     pub mode: String,
     pub remaining: u64,
     pub preset: String,
+    /// Preset that takes over when the current session ends
+    pub pending_preset: Option<String>,
     pub is_paused: bool,
     pub is_idle: bool,
     pub is_running: bool,
     pub task: String,
+    /// Completed work sessions in the current cycle
+    pub cycle_position: u32,
+    /// Work sessions per cycle; a long break follows the last one
+    pub cycle_length: u32,
+    pub auto_continue: bool,
+    /// Seconds until the next session starts on its own, while in the grace period
+    pub grace_remaining: Option<u64>,
+    pub flow_mode: bool,
+    /// Seconds counted past zero in flow mode
+    pub overtime: u64,
+    /// Interruptions logged in the current session
+    pub interruptions: u32,
+    /// Most recent session that was completed or terminated
+    pub last_session_id: Option<Uuid>,
+    /// Today's progress towards the daily goal, if one is set. Filled in by the
+    /// server, which keeps it cached.
+    pub goal: Option<GoalProgress>,
 }
 
-#[derive(Debug, Default, Clone)]
+impl TimerStatus {
+    /// The status `elapsed` later if only the clock moves in between. Lets a
+    /// client count down on its own between pushed updates.
+    pub fn advanced(&self, elapsed: Duration) -> TimerStatus {
+        let secs = elapsed.as_secs();
+        let mut status = self.clone();
+        if let Some(grace) = &mut status.grace_remaining {
+            *grace = grace.saturating_sub(secs);
+        }
+        if !status.is_running || status.is_paused {
+            return status;
+        }
+        if status.mode == TimerMode::Stopwatch.to_string() {
+            status.overtime += secs;
+        } else {
+            if status.flow_mode {
+                status.overtime += secs.saturating_sub(status.remaining);
+            }
+            status.remaining = status.remaining.saturating_sub(secs);
+        }
+        status
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct Timer {
+    clock: Arc<dyn Clock>,
     started_at: Option<Instant>,
     remaining: Duration,
     mode: TimerMode,
     timeset: Preset,
     durs: Durations,
+    /// Preset to switch to once the current session ends
+    pending_preset: Option<(Preset, Durations)>,
     paused: bool,
     idle: bool,
     auto_continue: bool,
     task_name: String,
     id: Option<Uuid>,
     events: VecDeque<LogEvent>,
+    /// Notifications waiting to be sent by the server
+    notices: VecDeque<Notice>,
+    /// Work sessions completed since the last long break. Only completed work
+    /// sessions count; skipping, terminating or resetting a work session leaves
+    /// it untouched. Leaving a long break, whether completed or skipped, starts
+    /// a new cycle.
+    cycle_pos: u32,
+    cycle_len: u32,
+    /// Countdown between an auto-continued session and the next one
+    grace: Duration,
+    /// When the next session starts on its own; `Some` only during the grace period
+    pending_start: Option<Instant>,
+    /// Time added to the current session with `extend`
+    extended: Duration,
+    /// Count past zero instead of completing when the time is up
+    flow_mode: bool,
+    /// The current session reached zero in flow mode and is counting overtime
+    overtime: bool,
+    /// Running time of the current session accumulated before the last pause
+    active: Duration,
+    /// Time the current session had when it started, plus extensions. Less
+    /// than the mode's duration for a session split off by `change_task`.
+    planned: Duration,
+    /// Time the current session spent paused before the current pause
+    paused_total: Duration,
+    /// Interruptions logged in the current session
+    interruptions: u32,
+    /// Most recent session that was completed or terminated
+    last_id: Option<Uuid>,
+    /// When the current session was paused
+    paused_at: Option<Instant>,
+    /// Paused sessions are terminated after this long
+    max_pause: Option<Duration>,
+    /// Last reset, mode switch or preset change; cleared when a session starts
+    undo: Option<Box<Undo>>,
+}
+
+impl Default for Timer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Timer {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
         let durs = Durations::default();
         let mode = TimerMode::Work;
         Self {
+            clock,
             started_at: None,
             remaining: mode.duration(&durs),
             mode,
             timeset: Preset::default(),
             durs,
+            pending_preset: None,
             paused: false,
             idle: true,
             auto_continue: true,
             task_name: String::new(),
             id: None,
             events: VecDeque::new(),
+            notices: VecDeque::new(),
+            cycle_pos: 0,
+            cycle_len: DEFAULT_CYCLE_LEN,
+            grace: Duration::from_secs(DEFAULT_GRACE_SECS),
+            pending_start: None,
+            extended: Duration::ZERO,
+            flow_mode: false,
+            overtime: false,
+            active: Duration::ZERO,
+            planned: Duration::ZERO,
+            paused_total: Duration::ZERO,
+            interruptions: 0,
+            last_id: None,
+            paused_at: None,
+            max_pause: None,
+            undo: None,
         }
     }
 
@@ -198,29 +595,40 @@ impl Timer {
         std::mem::take(&mut self.events).into_iter()
     }
 
+    pub fn drain_notices(&mut self) -> impl Iterator<Item = Notice> {
+        std::mem::take(&mut self.notices).into_iter()
+    }
+
     fn current_id(&self) -> Uuid {
         self.id.expect("Timer must have an ID when active")
     }
 
     fn start(&mut self) {
+        self.undo = None;
+        self.pending_start = None;
         self.idle = false;
-        self.started_at = Some(Instant::now());
+        self.started_at = Some(self.clock.now());
         self.id = Some(Uuid::new_v4());
+        self.planned = self.remaining;
         self.emit(LogEvent::Started {
             id: self.current_id(),
             timer_type: self.mode,
             task: self.task_name.clone(),
-            at: Local::now(),
+            at: self.clock.local_now(),
             remaining: self.get_remaining().as_secs(),
+            preset: Some(self.timeset.to_string()),
         });
     }
 
     fn resume(&mut self) {
-        self.started_at = Some(Instant::now());
+        self.started_at = Some(self.clock.now());
+        if let Some(since) = self.paused_at.take() {
+            self.paused_total += self.clock.elapsed(since);
+        }
         self.emit(LogEvent::Resumed {
             id: self.current_id(),
             task: self.task_name.clone(),
-            at: Local::now(),
+            at: self.clock.local_now(),
             remaining: self.get_remaining().as_secs(),
         });
     }
@@ -229,15 +637,22 @@ impl Timer {
         self.emit(LogEvent::Paused {
             id: self.current_id(),
             task: self.task_name.clone(),
-            at: Local::now(),
+            at: self.clock.local_now(),
             remaining: self.get_remaining().as_secs(),
         });
         if let Some(t0) = self.started_at.take() {
-            self.remaining = self.remaining.saturating_sub(t0.elapsed());
+            self.remaining = self.remaining.saturating_sub(self.clock.elapsed(t0));
+            self.active += self.clock.elapsed(t0);
         }
+        self.paused_at = Some(self.clock.now());
     }
 
     pub fn toggle(&mut self) {
+        if self.overtime {
+            // Stopping in overtime ends the session instead of pausing it
+            self.finish();
+            return;
+        }
         match (self.is_running(), self.is_paused()) {
             (false, false) => {
                 // Idle state -> start timer
@@ -262,16 +677,125 @@ impl Timer {
     }
 
     pub fn switch_mode(&mut self) {
+        if self.finish() {
+            return;
+        }
         if self.id.is_some() {
+            self.save_undo(self.id);
             self.persist_termination();
+        } else if self.mode.is_break() {
+            let id = Uuid::new_v4();
+            self.save_undo(Some(id));
+            self.emit(LogEvent::Skipped {
+                id,
+                timer_type: self.mode,
+                task: self.task_name.clone(),
+                at: self.clock.local_now(),
+            });
+        } else {
+            self.save_undo(None);
         }
-        self.mode = self.mode.toggle();
-        self.reset();
+        if self.mode == TimerMode::LongBreak {
+            self.cycle_pos = 0;
+        }
+        self.mode = self.next_mode();
+        self.clear_session();
     }
 
     fn complet_and_switch(&mut self) {
-        self.mode = self.mode.toggle();
-        self.reset();
+        match self.mode {
+            TimerMode::Work => self.cycle_pos += 1,
+            TimerMode::LongBreak => self.cycle_pos = 0,
+            TimerMode::Break | TimerMode::Stopwatch => {}
+        }
+        self.mode = self.next_mode();
+        self.clear_session();
+    }
+
+    /// The mode that follows the current one in the Pomodoro cycle
+    fn next_mode(&self) -> TimerMode {
+        match self.mode {
+            TimerMode::Work if self.cycle_pos >= self.cycle_len => TimerMode::LongBreak,
+            mode => mode.toggle(),
+        }
+    }
+
+    /// Moves to `mode`, terminating the current session if there is one
+    pub fn set_mode(&mut self, mode: TimerMode) {
+        if self.mode == mode {
+            return;
+        }
+        if self.id.is_some() {
+            self.persist_termination();
+        }
+        self.mode = mode;
+        self.clear_session();
+    }
+
+    pub fn set_cycle_length(&mut self, len: u32) {
+        self.cycle_len = len.max(1);
+    }
+
+    pub fn set_auto_continue(&mut self, enabled: bool) {
+        self.auto_continue = enabled;
+        if !enabled {
+            self.pending_start = None;
+        }
+    }
+
+    pub fn set_grace_period(&mut self, grace: Duration) {
+        self.grace = grace;
+    }
+
+    /// Terminate sessions that stay paused longer than `max`; `None` lets
+    /// them stay paused indefinitely
+    pub fn set_max_pause(&mut self, max: Option<Duration>) {
+        self.max_pause = max;
+    }
+
+    fn pause_expired(&self) -> bool {
+        match (self.max_pause, self.paused_at) {
+            (Some(max), Some(since)) => self.clock.elapsed(since) >= max,
+            _ => false,
+        }
+    }
+
+    /// Starts the next session right away, or after the grace period if one is set
+    fn schedule_next(&mut self) {
+        if self.grace.is_zero() {
+            self.toggle();
+        } else {
+            match self.clock.now().checked_add(self.grace) {
+                Some(at) => self.pending_start = Some(at),
+                None => self.toggle(),
+            }
+        }
+    }
+
+    pub fn is_in_grace(&self) -> bool {
+        self.pending_start.is_some()
+    }
+
+    fn grace_remaining(&self) -> Option<Duration> {
+        self.pending_start
+            .map(|deadline| deadline.saturating_duration_since(self.clock.now()))
+    }
+
+    /// Keeps the timer idle instead of starting the pending session.
+    /// Returns `false` if nothing was pending.
+    pub fn cancel_grace(&mut self) -> bool {
+        self.pending_start.take().is_some()
+    }
+
+    /// Skips the pending session without recording it and restarts the grace
+    /// period for the one after. Returns `false` if nothing was pending.
+    pub fn skip_grace(&mut self) -> bool {
+        if !self.is_in_grace() {
+            return false;
+        }
+        self.switch_mode();
+        self.schedule_next();
+        true
     }
 
     pub fn is_running(&self) -> bool {
@@ -282,48 +806,334 @@ impl Timer {
         self.paused
     }
 
+    /// Whether the session reached zero in flow mode and counts overtime
+    pub fn is_overtime(&self) -> bool {
+        self.overtime
+    }
+
     pub fn is_idle(&self) -> bool {
         self.idle
     }
 
+    /// Abandons the current session, logging it as terminated by a reset. The
+    /// previous state can be brought back with `undo` for a short while.
     pub fn reset(&mut self) {
+        self.save_undo(self.id);
+        if self.id.is_some() {
+            self.emit_termination(Some(TerminationReason::Reset));
+        }
+        self.clear_session();
+    }
+
+    /// Goes back to the state before the last reset, mode switch or preset
+    /// change, if that was less than `UNDO_WINDOW` ago and no session has
+    /// started since. A session it brings back continues under its own id.
+    pub fn undo(&mut self) -> bool {
+        let Some(undo) = self.undo.take() else {
+            return false;
+        };
+        if self.clock.elapsed(undo.at) > UNDO_WINDOW {
+            return false;
+        }
+        let session = undo.session;
+        self.started_at = session.started_at;
+        self.remaining = session.remaining;
+        self.mode = session.mode;
+        self.timeset = session.timeset;
+        self.durs = session.durs;
+        self.paused = session.paused;
+        self.idle = session.idle;
+        self.task_name = session.task_name;
+        self.id = session.id;
+        self.cycle_pos = session.cycle_pos;
+        self.pending_start = session.pending_start;
+        self.extended = session.extended;
+        self.overtime = session.overtime;
+        self.active = session.active;
+        self.planned = session.planned;
+        self.paused_total = session.paused_total;
+        self.interruptions = session.interruptions;
+        self.last_id = session.last_id;
+        self.paused_at = session.paused_at;
+        if let Some(id) = undo.undone {
+            self.emit(LogEvent::Undone {
+                id,
+                task: self.task_name.clone(),
+                at: self.clock.local_now(),
+                remaining: self.get_remaining().as_secs(),
+            });
+        }
+        true
+    }
+
+    fn save_undo(&mut self, undone: Option<Uuid>) {
+        let session = SessionState {
+            started_at: self.started_at,
+            remaining: self.remaining,
+            mode: self.mode,
+            timeset: self.timeset.clone(),
+            durs: self.durs,
+            paused: self.paused,
+            idle: self.idle,
+            task_name: self.task_name.clone(),
+            id: self.id,
+            cycle_pos: self.cycle_pos,
+            pending_start: self.pending_start,
+            extended: self.extended,
+            overtime: self.overtime,
+            active: self.active,
+            planned: self.planned,
+            paused_total: self.paused_total,
+            interruptions: self.interruptions,
+            last_id: self.last_id,
+            paused_at: self.paused_at,
+        };
+        self.undo = Some(Box::new(Undo {
+            at: self.clock.now(),
+            session,
+            undone,
+        }));
+    }
+
+    fn clear_session(&mut self) {
+        if let Some((p, durs)) = self.pending_preset.take() {
+            self.timeset = p;
+            self.durs = durs;
+        }
         self.idle = true;
         self.remaining = self.mode.duration(&self.durs);
         self.started_at = None;
         self.id = None;
         self.paused = false;
+        self.pending_start = None;
+        self.extended = Duration::ZERO;
+        self.overtime = false;
+        self.active = Duration::ZERO;
+        self.planned = Duration::ZERO;
+        self.paused_total = Duration::ZERO;
+        self.interruptions = 0;
+        self.paused_at = None;
+    }
+
+    /// Adds time to the running or paused session. Returns `false` if there is
+    /// no session to extend.
+    pub fn extend(&mut self, by: Duration) -> bool {
+        if !self.can_extend() {
+            return false;
+        }
+        let (Some(remaining), Some(extended), Some(planned)) = (
+            self.remaining.checked_add(by),
+            self.extended.checked_add(by),
+            self.planned.checked_add(by),
+        ) else {
+            return false;
+        };
+        if self.clock.now().checked_add(remaining).is_none() {
+            return false;
+        }
+        let overtime = self.get_overtime();
+        self.remaining = remaining;
+        self.extended = extended;
+        self.planned = planned;
+        if self.overtime && by > overtime {
+            self.overtime = false;
+        }
+        self.emit(LogEvent::Extended {
+            id: self.current_id(),
+            task: self.task_name.clone(),
+            at: self.clock.local_now(),
+            added: by.as_secs(),
+            remaining: self.get_remaining().as_secs(),
+        });
+        true
+    }
+
+    /// Whether there is a countdown session that `extend` can lengthen
+    pub fn can_extend(&self) -> bool {
+        self.id.is_some() && self.mode != TimerMode::Stopwatch
     }
 
     pub fn get_remaining(&self) -> Duration {
         match self.started_at {
-            Some(t0) => self.remaining.saturating_sub(t0.elapsed()),
+            Some(t0) => self.remaining.saturating_sub(self.clock.elapsed(t0)),
             None => self.remaining,
         }
     }
 
     pub fn update(&mut self) {
-        if let Some(t0) = self.started_at.as_ref() {
-            if self.remaining > t0.elapsed() {
+        if let Some(deadline) = self.pending_start {
+            if self.clock.now() >= deadline {
+                self.toggle();
+            }
+            return;
+        }
+        if self.pause_expired() {
+            self.notify(NoticeKind::PauseExpired);
+            self.emit_termination(Some(TerminationReason::MaxPause));
+            self.clear_session();
+            return;
+        }
+        if self.mode == TimerMode::Stopwatch {
+            // No deadline; the session runs until it is finished
+            return;
+        }
+        if let Some(t0) = self.started_at {
+            if self.remaining > self.clock.elapsed(t0) || self.overtime {
                 return;
             }
-            self.emit(LogEvent::Completed {
-                id: self.current_id(),
+            if self.flow_mode {
+                // Keep counting past zero; the user finishes the session
+                self.overtime = true;
+                self.notify(NoticeKind::Overtime);
+                return;
+            }
+            self.notify(NoticeKind::Completed);
+            self.complete();
+        }
+    }
+
+    fn notify(&mut self, kind: NoticeKind) {
+        self.notices.push_back(Notice {
+            kind,
+            mode: self.mode,
+            task: self.task_name.clone(),
+            detail: String::new(),
+        });
+        if self.notices.len() > MAX_EMIT_EVENTS {
+            self.notices.pop_front();
+        }
+    }
+
+    /// Logs the current session as Completed, overtime included, and moves on
+    /// to the next mode
+    fn complete(&mut self) {
+        self.emit(LogEvent::Completed {
+            id: self.current_id(),
+            task: self.task_name.clone(),
+            at: self.clock.local_now(),
+            active_secs: self.work_time().as_secs(),
+            planned_secs: self.planned.as_secs(),
+            paused_secs: self.paused_time().as_secs(),
+        });
+        self.last_id = self.id;
+        let was_stopwatch = self.mode == TimerMode::Stopwatch;
+        self.complet_and_switch();
+        if self.auto_continue && !was_stopwatch {
+            self.schedule_next();
+        }
+    }
+
+    /// Ends a session that is counting overtime or a stopwatch session.
+    /// Returns `false` if there is no such session.
+    pub fn finish(&mut self) -> bool {
+        let stopwatch = self.mode == TimerMode::Stopwatch && self.id.is_some();
+        if !self.overtime && !stopwatch {
+            return false;
+        }
+        self.complete();
+        true
+    }
+
+    pub fn set_flow_mode(&mut self, enabled: bool) {
+        self.flow_mode = enabled;
+        if !enabled && self.overtime {
+            self.finish();
+        }
+    }
+
+    /// Start of a work-hours block: turns auto-continue on and starts a work
+    /// session unless one is already running or paused
+    pub fn schedule_start(&mut self) {
+        self.auto_continue = true;
+        if self.id.is_none() {
+            self.set_mode(TimerMode::Work);
+            self.start();
+        }
+        self.emit(LogEvent::ScheduleStarted {
+            id: self.current_id(),
+            task: self.task_name.clone(),
+            at: self.clock.local_now(),
+            remaining: self.get_remaining().as_secs(),
+        });
+    }
+
+    /// End of a work-hours block: turns auto-continue off so the timer stops
+    /// after the current session. Recorded against the current session, or the
+    /// last one if none is active.
+    pub fn schedule_stop(&mut self) {
+        self.set_auto_continue(false);
+        if let Some(id) = self.id.or(self.last_id) {
+            self.emit(LogEvent::ScheduleStopped {
+                id,
                 task: self.task_name.clone(),
-                at: Local::now(),
-                work_secs: self.mode.duration(&self.durs).as_secs(),
+                at: self.clock.local_now(),
+                remaining: self.get_remaining().as_secs(),
             });
+        }
+    }
 
-            let notification_msg = format!("{}: {}", self.mode, self.task_name);
-            let _ = Notification::new()
-                .summary("Completed")
-                .body(&notification_msg)
-                .icon("clock")
-                .show();
+    /// Logs an interruption of the current work or stopwatch session. Returns
+    /// `false` if there is no such session.
+    pub fn interrupt(&mut self, kind: InterruptionKind, note: Option<String>) -> bool {
+        if self.id.is_none() || !matches!(self.mode, TimerMode::Work | TimerMode::Stopwatch) {
+            return false;
+        }
+        self.interruptions += 1;
+        self.emit(LogEvent::Interrupted {
+            id: self.current_id(),
+            task: self.task_name.clone(),
+            at: self.clock.local_now(),
+            kind,
+            note,
+            remaining: self.get_remaining().as_secs(),
+        });
+        true
+    }
 
-            self.complet_and_switch();
-            if self.auto_continue {
-                self.toggle();
-            }
+    /// Time the current session has been running, excluding pauses
+    fn active_time(&self) -> Duration {
+        match self.started_at {
+            Some(t0) => self.active + self.clock.elapsed(t0),
+            None => self.active,
+        }
+    }
+
+    /// Time the current session actually ran. Capped at what was planned plus
+    /// overtime, so a completion noticed a tick late doesn't count the lag.
+    fn work_time(&self) -> Duration {
+        self.active_time().min(self.planned + self.get_overtime())
+    }
+
+    /// Time the current session spent paused, including an ongoing pause
+    fn paused_time(&self) -> Duration {
+        match self.paused_at {
+            Some(since) => self.paused_total + self.clock.elapsed(since),
+            None => self.paused_total,
+        }
+    }
+
+    /// Time counted past zero in flow mode, or the elapsed time of a stopwatch
+    pub fn get_overtime(&self) -> Duration {
+        if self.mode == TimerMode::Stopwatch {
+            return self.active_time();
+        }
+        match self.started_at {
+            Some(t0) if self.overtime => self.clock.elapsed(t0).saturating_sub(self.remaining),
+            _ => Duration::ZERO,
+        }
+    }
+
+    pub fn state(&self) -> TimerState {
+        let run = if self.is_paused() {
+            RunState::Paused
+        } else if self.is_running() {
+            RunState::Running
+        } else {
+            RunState::Idle
+        };
+        TimerState {
+            mode: self.mode,
+            run,
         }
     }
 
@@ -332,10 +1142,22 @@ impl Timer {
             task: self.get_task_name().to_string(),
             remaining: self.get_remaining().as_secs(),
             preset: self.get_preset().to_string(),
+            pending_preset: self.pending_preset.as_ref().map(|(p, _)| p.to_string()),
             is_paused: self.is_paused(),
             is_idle: self.is_idle(),
             is_running: self.is_running(),
             mode: self.get_mode().to_string(),
+            cycle_position: self.cycle_pos,
+            cycle_length: self.cycle_len,
+            auto_continue: self.auto_continue,
+            grace_remaining: self
+                .grace_remaining()
+                .map(|d| d.as_millis().div_ceil(1000) as u64),
+            flow_mode: self.flow_mode,
+            overtime: self.get_overtime().as_secs(),
+            interruptions: self.interruptions,
+            last_session_id: self.last_id,
+            goal: None,
         }
     }
 
@@ -351,6 +1173,34 @@ impl Timer {
         &self.timeset
     }
 
+    /// Re-attaches the timer to a session that was open when the server stopped.
+    /// No event is emitted; the session continues under its original id.
+    pub fn restore(
+        &mut self,
+        id: Uuid,
+        mode: TimerMode,
+        task: &str,
+        progress: SessionProgress,
+        paused: bool,
+    ) {
+        self.clear_session();
+        self.mode = mode;
+        self.task_name = task.into();
+        self.id = Some(id);
+        self.remaining = progress.remaining;
+        self.planned = progress.planned;
+        self.active = progress.active;
+        self.paused_total = progress.paused;
+        self.idle = false;
+        self.paused = paused;
+        if paused {
+            // Downtime doesn't count towards the maximum pause
+            self.paused_at = Some(self.clock.now());
+        } else {
+            self.started_at = Some(self.clock.now());
+        }
+    }
+
     pub fn set_task_name(&mut self, new_task_name: &str) {
         self.task_name = new_task_name.into();
     }
@@ -363,18 +1213,23 @@ impl Timer {
             self.persist_termination();
             // Snapshot remaining time before resetting started_at
             if let Some(t0) = self.started_at.take() {
-                self.remaining = self.remaining.saturating_sub(t0.elapsed());
+                self.remaining = self.remaining.saturating_sub(self.clock.elapsed(t0));
             }
             // Update name and start fresh session with remaining time
+            self.active = Duration::ZERO;
+            self.planned = self.remaining;
+            self.paused_total = Duration::ZERO;
+            self.interruptions = 0;
             self.task_name = new_task_name.into();
             self.id = Some(Uuid::new_v4());
-            self.started_at = Some(Instant::now());
+            self.started_at = Some(self.clock.now());
             self.emit(LogEvent::Started {
                 id: self.current_id(),
                 timer_type: self.mode,
                 task: self.task_name.clone(),
-                at: Local::now(),
+                at: self.clock.local_now(),
                 remaining: self.remaining.as_secs(),
+                preset: Some(self.timeset.to_string()),
             });
         } else {
             self.task_name = new_task_name.into();
@@ -382,31 +1237,70 @@ impl Timer {
     }
 
     pub fn set_preset(&mut self, p: Preset) {
-        if self.timeset == p {
+        match Durations::for_preset(&p) {
+            Some(durs) => self.apply_preset(p, durs),
+            None => log::warn!("Preset {p} has no built-in durations, ignoring."),
+        }
+    }
+
+    pub fn set_custom_preset(&mut self, name: &str, durs: Durations) {
+        self.apply_preset(Preset::Custom(name.into()), durs);
+    }
+
+    /// Switches to `p` once the current session ends, keeping the session
+    /// going. Without a session the preset applies right away.
+    pub fn set_pending_preset(&mut self, p: Preset, durs: Durations) {
+        if self.id.is_none() {
+            self.apply_preset(p, durs);
+        } else if self.timeset == p && self.durs == durs {
+            self.pending_preset = None;
+        } else {
+            self.pending_preset = Some((p, durs));
+        }
+    }
+
+    fn apply_preset(&mut self, p: Preset, durs: Durations) {
+        if self.timeset == p && self.durs == durs {
             log::info!("Already using {:?} preset.", p);
+            self.pending_preset = None;
             return;
         }
+        self.save_undo(self.id);
         if self.id.is_some() {
             self.persist_termination();
         }
-        let new = Durations::for_preset(p);
-        self.durs = new;
+        self.pending_preset = None;
+        self.durs = durs;
         self.timeset = p;
-        self.reset();
+        self.clear_session();
     }
 
     // TODO: is it possible the gather the emit logic to one function?
     pub fn persist_termination(&mut self) {
+        self.emit_termination(None);
+    }
+
+    fn emit_termination(&mut self, reason: Option<TerminationReason>) {
         self.emit(LogEvent::Terminated {
             id: self.current_id(),
             task: self.task_name.clone(),
-            at: Local::now(),
+            at: self.clock.local_now(),
             remaining: self.get_remaining().as_secs(),
-            work_secs: (self.mode.duration(&self.durs) - self.get_remaining()).as_secs(),
+            active_secs: self.work_time().as_secs(),
+            planned_secs: self.planned.as_secs(),
+            paused_secs: self.paused_time().as_secs(),
+            reason,
         });
+        self.last_id = self.id;
     }
 }
 
+#[cfg(test)]
+fn manual_timer() -> (Timer, Arc<crate::clock::ManualClock>) {
+    let clock = Arc::new(crate::clock::ManualClock::new());
+    (Timer::with_clock(clock.clone()), clock)
+}
+
 #[test]
 fn init_timer_mode() {
     let t = Timer::new();
@@ -415,6 +1309,569 @@ fn init_timer_mode() {
     assert_eq!(t.durs, Durations::default());
 }
 
+#[test]
+fn custom_preset_durations() {
+    let mut t = Timer::new();
+    let durs = Durations::new(Duration::from_secs(52 * MIN), Duration::from_secs(17 * MIN));
+    t.set_custom_preset("52/17", durs);
+    assert_eq!(t.get_preset(), &Preset::Custom("52/17".into()));
+    assert_eq!(t.remaining, Duration::from_secs(52 * MIN));
+    t.switch_mode();
+    assert_eq!(t.remaining, Duration::from_secs(17 * MIN));
+}
+
+#[test]
+fn long_break_after_cycle() {
+    let mut t = Timer::new();
+    for _ in 0..DEFAULT_CYCLE_LEN - 1 {
+        t.complet_and_switch(); // Work done
+        assert_eq!(t.mode, TimerMode::Break);
+        t.complet_and_switch(); // Break done
+        assert_eq!(t.mode, TimerMode::Work);
+    }
+    t.complet_and_switch();
+    assert_eq!(t.mode, TimerMode::LongBreak);
+    assert_eq!(t.cycle_pos, DEFAULT_CYCLE_LEN);
+    assert_eq!(t.remaining, Duration::from_secs(15 * MIN));
+    t.complet_and_switch();
+    assert_eq!(t.mode, TimerMode::Work);
+    assert_eq!(t.cycle_pos, 0);
+}
+
+#[test]
+fn skipped_work_does_not_count() {
+    let mut t = Timer::new();
+    t.set_cycle_length(1);
+    t.switch_mode();
+    assert_eq!(t.mode, TimerMode::Break);
+    assert_eq!(t.cycle_pos, 0);
+    t.switch_mode();
+    t.complet_and_switch();
+    assert_eq!(t.mode, TimerMode::LongBreak);
+    // Skipping the long break still starts a new cycle
+    t.switch_mode();
+    assert_eq!(t.mode, TimerMode::Work);
+    assert_eq!(t.cycle_pos, 0);
+}
+
+#[test]
+fn grace_cancel_and_skip() {
+    let mut t = Timer::new();
+    assert!(!t.cancel_grace());
+    assert!(!t.skip_grace());
+
+    t.complet_and_switch();
+    t.schedule_next();
+    assert!(t.is_in_grace());
+    assert!(t.is_idle());
+    assert_eq!(
+        t.get_timer_status().grace_remaining,
+        Some(DEFAULT_GRACE_SECS)
+    );
+    assert!(t.cancel_grace());
+    assert!(t.is_idle());
+    assert_eq!(t.get_timer_status().grace_remaining, None);
+
+    t.schedule_next();
+    assert!(t.skip_grace());
+    assert_eq!(t.mode, TimerMode::Work);
+    assert!(t.is_in_grace());
+    assert!(matches!(
+        t.drain_events().collect::<Vec<_>>().as_slice(),
+        [LogEvent::Skipped {
+            timer_type: TimerMode::Break,
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn grace_zero_starts_immediately() {
+    let mut t = Timer::new();
+    t.set_grace_period(Duration::ZERO);
+    t.schedule_next();
+    assert!(!t.is_in_grace());
+    assert!(t.is_running());
+}
+
+#[test]
+fn disabling_auto_continue_cancels_grace() {
+    let mut t = Timer::new();
+    t.schedule_next();
+    t.set_auto_continue(false);
+    assert!(!t.is_in_grace());
+    assert!(!t.get_timer_status().auto_continue);
+}
+
+#[test]
+fn restore_paused_session() {
+    let mut t = Timer::new();
+    let id = Uuid::new_v4();
+    let progress = SessionProgress {
+        planned: Duration::from_secs(300),
+        remaining: Duration::from_secs(120),
+        paused: Duration::ZERO,
+        active: Duration::from_secs(180),
+    };
+    t.restore(id, TimerMode::Break, "t", progress, true);
+    assert!(t.is_paused());
+    assert!(!t.is_idle());
+    assert_eq!(t.get_remaining(), Duration::from_secs(120));
+    t.toggle();
+    assert!(t.is_running());
+    assert!(matches!(
+        t.drain_events().next(),
+        Some(LogEvent::Resumed { id: resumed, .. }) if resumed == id
+    ));
+}
+
+#[test]
+fn extend_session() {
+    let (mut t, _clock) = manual_timer();
+    assert!(!t.extend(Duration::from_secs(5 * MIN)));
+    t.toggle();
+    assert!(t.extend(Duration::from_secs(5 * MIN)));
+    assert_eq!(t.get_remaining(), Duration::from_secs(30 * MIN));
+    let events: Vec<_> = t.drain_events().collect();
+    assert!(matches!(
+        events.last(),
+        Some(LogEvent::Extended { added: 300, .. })
+    ));
+    t.persist_termination();
+    assert!(matches!(
+        t.drain_events().next(),
+        Some(LogEvent::Terminated { active_secs: 0, .. })
+    ));
+    t.reset();
+    assert_eq!(t.get_remaining(), Duration::from_secs(25 * MIN));
+}
+
+#[test]
+fn flow_mode_counts_overtime() {
+    let (mut t, clock) = manual_timer();
+    t.set_preset(Preset::Test);
+    t.set_flow_mode(true);
+    t.set_auto_continue(false);
+    t.toggle();
+    clock.advance(Duration::from_secs(8));
+    t.update();
+    assert!(t.overtime);
+    assert!(t.is_running());
+    assert_eq!(t.get_overtime().as_secs(), 3);
+    assert_eq!(t.get_remaining(), Duration::ZERO);
+
+    // Stopping logs the session as Completed with the overtime included
+    t.toggle();
+    assert!(!t.overtime);
+    assert_eq!(t.mode, TimerMode::Break);
+    assert!(t.is_idle());
+    let events: Vec<_> = t.drain_events().collect();
+    assert!(matches!(
+        events.last(),
+        Some(LogEvent::Completed { active_secs: 8, id, .. })
+            if t.get_timer_status().last_session_id == Some(*id)
+    ));
+}
+
+#[test]
+fn session_completes_and_auto_continues() {
+    let (mut t, clock) = manual_timer();
+    t.set_preset(Preset::Test);
+    t.toggle();
+    clock.advance(Duration::from_secs(4));
+    t.update();
+    assert_eq!(t.get_remaining(), Duration::from_secs(1));
+    assert_eq!(t.mode, TimerMode::Work);
+
+    clock.advance(Duration::from_secs(1));
+    t.update();
+    assert_eq!(t.mode, TimerMode::Break);
+    assert_eq!(t.cycle_pos, 1);
+    assert!(t.is_in_grace());
+    assert_eq!(
+        t.grace_remaining(),
+        Some(Duration::from_secs(DEFAULT_GRACE_SECS))
+    );
+
+    clock.advance(Duration::from_secs(DEFAULT_GRACE_SECS));
+    t.update();
+    assert!(t.is_running());
+    assert_eq!(t.mode, TimerMode::Break);
+    let notices: Vec<_> = t.drain_notices().map(|n| (n.kind, n.mode)).collect();
+    assert_eq!(notices, [(NoticeKind::Completed, TimerMode::Work)]);
+    let events: Vec<_> = t.drain_events().collect();
+    assert!(matches!(
+        events.as_slice(),
+        [
+            LogEvent::Started {
+                timer_type: TimerMode::Work,
+                ..
+            },
+            LogEvent::Completed { active_secs: 5, .. },
+            LogEvent::Started {
+                timer_type: TimerMode::Break,
+                ..
+            },
+        ]
+    ));
+}
+
+#[test]
+fn pause_freezes_remaining() {
+    let (mut t, clock) = manual_timer();
+    t.toggle();
+    clock.advance(Duration::from_secs(10 * MIN));
+    t.toggle();
+    clock.advance(Duration::from_secs(60 * MIN));
+    t.update();
+    assert!(t.is_paused());
+    assert_eq!(t.get_remaining(), Duration::from_secs(15 * MIN));
+
+    t.toggle();
+    clock.advance(Duration::from_secs(15 * MIN));
+    t.set_auto_continue(false);
+    t.update();
+    assert_eq!(t.mode, TimerMode::Break);
+    assert!(t.is_idle());
+    assert!(!t.is_in_grace());
+}
+
+#[test]
+fn completed_session_records_active_time_and_pauses() {
+    let (mut t, clock) = manual_timer();
+    t.set_auto_continue(false);
+    t.toggle();
+    clock.advance(Duration::from_secs(10 * MIN));
+    t.toggle();
+    clock.advance(Duration::from_secs(7 * MIN));
+    t.toggle();
+    clock.advance(Duration::from_secs(15 * MIN));
+    t.update();
+    assert!(matches!(
+        t.drain_events().last(),
+        Some(LogEvent::Completed {
+            active_secs: 1500,
+            planned_secs: 1500,
+            paused_secs: 420,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn split_session_counts_only_its_own_time() {
+    let (mut t, clock) = manual_timer();
+    t.set_auto_continue(false);
+    t.toggle();
+    clock.advance(Duration::from_secs(10 * MIN));
+    t.change_task("second");
+    let events: Vec<_> = t.drain_events().collect();
+    assert!(matches!(
+        events.as_slice(),
+        [
+            LogEvent::Started { .. },
+            LogEvent::Terminated {
+                active_secs: 600,
+                planned_secs: 1500,
+                ..
+            },
+            LogEvent::Started {
+                remaining: 900,
+                preset: Some(_),
+                ..
+            },
+        ]
+    ));
+
+    clock.advance(Duration::from_secs(15 * MIN));
+    t.update();
+    assert!(matches!(
+        t.drain_events().last(),
+        Some(LogEvent::Completed {
+            active_secs: 900,
+            planned_secs: 900,
+            paused_secs: 0,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn reset_is_logged_and_can_be_undone() {
+    let (mut t, clock) = manual_timer();
+    assert!(!t.undo());
+    t.toggle();
+    let id = t.id;
+    clock.advance(Duration::from_secs(10 * MIN));
+    t.reset();
+    assert!(t.is_idle());
+    assert!(matches!(
+        t.drain_events().last(),
+        Some(LogEvent::Terminated {
+            active_secs: 600,
+            reason: Some(TerminationReason::Reset),
+            ..
+        })
+    ));
+
+    clock.advance(Duration::from_secs(10));
+    assert!(t.undo());
+    assert!(t.is_running());
+    assert_eq!(t.id, id);
+    assert_eq!(t.get_remaining(), Duration::from_secs(15 * MIN - 10));
+    assert!(matches!(
+        t.drain_events().collect::<Vec<_>>().as_slice(),
+        [LogEvent::Undone { remaining, .. }] if *remaining == 15 * MIN - 10
+    ));
+    // Only one step back
+    assert!(!t.undo());
+}
+
+#[test]
+fn undo_keeps_settings_changed_since() {
+    let (mut t, clock) = manual_timer();
+    t.toggle();
+    clock.advance(Duration::from_secs(MIN));
+    t.reset();
+    t.set_auto_continue(false);
+    t.set_flow_mode(true);
+    t.set_max_pause(Some(Duration::from_secs(5 * MIN)));
+    assert!(t.undo());
+    assert!(t.is_running());
+    assert!(!t.auto_continue);
+    assert!(t.flow_mode);
+    assert_eq!(t.max_pause, Some(Duration::from_secs(5 * MIN)));
+}
+
+#[test]
+fn undo_expires_and_is_cleared_by_a_new_session() {
+    let (mut t, clock) = manual_timer();
+    t.set_preset(Preset::Long);
+    clock.advance(UNDO_WINDOW + Duration::from_secs(1));
+    assert!(!t.undo());
+    assert_eq!(t.get_preset(), &Preset::Long);
+
+    t.set_preset(Preset::Short);
+    t.toggle();
+    assert!(!t.undo());
+    assert_eq!(t.get_preset(), &Preset::Short);
+}
+
+#[test]
+fn undo_skipped_break() {
+    let (mut t, _clock) = manual_timer();
+    t.switch_mode();
+    assert_eq!(t.mode, TimerMode::Break);
+    t.switch_mode();
+    assert_eq!(t.mode, TimerMode::Work);
+    let skipped = match t.drain_events().last() {
+        Some(LogEvent::Skipped { id, .. }) => id,
+        other => panic!("expected Skipped, got {other:?}"),
+    };
+    assert!(t.undo());
+    assert_eq!(t.mode, TimerMode::Break);
+    assert!(t.is_idle());
+    assert!(matches!(
+        t.drain_events().last(),
+        Some(LogEvent::Undone { id, .. }) if id == skipped
+    ));
+}
+
+#[test]
+fn pending_preset_applies_when_session_ends() {
+    let (mut t, clock) = manual_timer();
+    t.set_auto_continue(false);
+    t.toggle();
+    let id = t.id;
+    t.set_pending_preset(Preset::Long, Durations::LONG);
+    assert_eq!(t.id, id);
+    assert_eq!(t.get_preset(), &Preset::Short);
+    assert_eq!(t.get_timer_status().pending_preset.as_deref(), Some("Long"));
+    assert!(
+        !t.drain_events()
+            .any(|e| matches!(e, LogEvent::Terminated { .. }))
+    );
+
+    clock.advance(Duration::from_secs(25 * MIN));
+    t.update();
+    assert_eq!(t.mode, TimerMode::Break);
+    assert_eq!(t.get_preset(), &Preset::Long);
+    assert_eq!(t.get_remaining(), Durations::LONG.brk);
+    assert_eq!(t.get_timer_status().pending_preset, None);
+}
+
+#[test]
+fn pending_preset_without_session_applies_now() {
+    let (mut t, _clock) = manual_timer();
+    t.set_pending_preset(Preset::Long, Durations::LONG);
+    assert_eq!(t.get_preset(), &Preset::Long);
+    assert_eq!(t.get_remaining(), Durations::LONG.work);
+
+    // Choosing the current preset again drops a pending change
+    t.toggle();
+    t.set_pending_preset(Preset::Test, Durations::TEST);
+    t.set_pending_preset(Preset::Long, Durations::LONG);
+    assert_eq!(t.get_timer_status().pending_preset, None);
+}
+
+#[test]
+fn long_pause_terminates_session() {
+    let (mut t, clock) = manual_timer();
+    t.set_max_pause(Some(Duration::from_secs(30 * MIN)));
+    t.toggle();
+    clock.advance(Duration::from_secs(10 * MIN));
+    t.toggle();
+    clock.advance(Duration::from_secs(29 * MIN));
+    t.update();
+    assert!(t.is_paused());
+
+    // Resuming restarts the allowance
+    t.toggle();
+    t.toggle();
+    clock.advance(Duration::from_secs(29 * MIN));
+    t.update();
+    assert!(t.is_paused());
+    clock.advance(Duration::from_secs(MIN));
+    t.update();
+    assert!(t.is_idle());
+    assert_eq!(t.mode, TimerMode::Work);
+    assert!(matches!(
+        t.drain_events().last(),
+        Some(LogEvent::Terminated {
+            active_secs: 600,
+            reason: Some(TerminationReason::MaxPause),
+            ..
+        })
+    ));
+    assert_eq!(
+        t.drain_notices().map(|n| n.kind).collect::<Vec<_>>(),
+        [NoticeKind::PauseExpired]
+    );
+}
+
+#[test]
+fn interrupt_work_session() {
+    let (mut t, _clock) = manual_timer();
+    assert!(!t.interrupt(InterruptionKind::Internal, None));
+    t.toggle();
+    assert!(t.interrupt(InterruptionKind::External, Some("phone".into())));
+    assert!(t.interrupt(InterruptionKind::Internal, None));
+    assert!(t.is_running());
+    assert_eq!(t.get_timer_status().interruptions, 2);
+    assert!(matches!(
+        t.drain_events().nth(1),
+        Some(LogEvent::Interrupted {
+            kind: InterruptionKind::External,
+            note: Some(note),
+            ..
+        }) if note == "phone"
+    ));
+
+    t.switch_mode();
+    assert_eq!(t.get_timer_status().interruptions, 0);
+    t.toggle();
+    assert!(!t.interrupt(InterruptionKind::Internal, None));
+}
+
+#[test]
+fn schedule_starts_work_and_stops_auto_continue() {
+    let (mut t, clock) = manual_timer();
+    t.set_auto_continue(false);
+    t.switch_mode();
+    assert_eq!(t.mode, TimerMode::Break);
+
+    t.schedule_start();
+    assert_eq!(t.mode, TimerMode::Work);
+    assert!(t.is_running());
+    assert!(t.get_timer_status().auto_continue);
+    let events: Vec<_> = t.drain_events().collect();
+    assert!(matches!(
+        events.as_slice(),
+        [LogEvent::Started { id: a, .. }, LogEvent::ScheduleStarted { id: b, .. }] if a == b
+    ));
+
+    t.schedule_stop();
+    assert!(!t.get_timer_status().auto_continue);
+    assert!(t.is_running());
+    clock.advance(Duration::from_secs(25 * MIN));
+    t.update();
+    assert!(t.is_idle());
+    assert!(!t.is_in_grace());
+    assert!(matches!(
+        t.drain_events().next(),
+        Some(LogEvent::ScheduleStopped { .. })
+    ));
+}
+
+#[test]
+fn finish_requires_overtime() {
+    let mut t = Timer::new();
+    t.toggle();
+    assert!(!t.finish());
+    assert!(t.is_running());
+}
+
+#[test]
+fn stopwatch_counts_up_until_finished() {
+    let (mut t, clock) = manual_timer();
+    t.set_mode(TimerMode::Stopwatch);
+    assert!(!t.finish());
+    t.toggle();
+    clock.advance(Duration::from_secs(60));
+    t.update();
+    assert!(t.is_running());
+    assert!(!t.extend(Duration::from_secs(60)));
+
+    // Time spent paused is not counted
+    t.toggle();
+    assert!(t.is_paused());
+    clock.advance(Duration::from_secs(600));
+    assert_eq!(t.get_overtime().as_secs(), 60);
+    t.toggle();
+    clock.advance(Duration::from_secs(30));
+    // Turning flow mode off only ends an overtime countdown
+    t.set_flow_mode(false);
+    assert!(t.is_running());
+    assert!(t.finish());
+    assert_eq!(t.mode, TimerMode::Work);
+    assert!(t.is_idle());
+    assert!(!t.is_in_grace());
+    let events: Vec<_> = t.drain_events().collect();
+    assert!(matches!(
+        events.first(),
+        Some(LogEvent::Started {
+            timer_type: TimerMode::Stopwatch,
+            ..
+        })
+    ));
+    assert!(matches!(
+        events.last(),
+        Some(LogEvent::Completed {
+            active_secs: 90,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn status_from_older_server_parses() {
+    let json = r#"{"mode": "Work", "remaining": 1500, "preset": "Short", "is_paused": false,
+        "is_idle": true, "is_running": false, "task": ""}"#;
+    let status: TimerStatus = serde_json::from_str(json).unwrap();
+    assert_eq!(status.remaining, 1500);
+    assert_eq!(status.pending_preset, None);
+    assert_eq!(status.goal, None);
+}
+
+#[test]
+fn preset_serializes_as_name() {
+    assert_eq!(serde_json::to_string(&Preset::Long).unwrap(), "\"Long\"");
+    assert_eq!(
+        serde_json::from_str::<Preset>("\"deep\"").unwrap(),
+        Preset::Custom("deep".into())
+    );
+}
+
 #[test]
 fn timer_mode_toggle() {
     let mut ts = TimerMode::default();
@@ -423,4 +1880,47 @@ fn timer_mode_toggle() {
     assert_eq!(ts, TimerMode::Break);
     ts = ts.toggle();
     assert_eq!(ts, TimerMode::Work);
+    assert_eq!(TimerMode::LongBreak.toggle(), TimerMode::Work);
+}
+
+#[test]
+fn status_advances_like_the_timer() {
+    let status = TimerStatus {
+        mode: "Work".into(),
+        remaining: 10,
+        is_running: true,
+        ..Default::default()
+    };
+    assert_eq!(status.advanced(Duration::from_secs(4)).remaining, 6);
+    assert_eq!(status.advanced(Duration::from_secs(15)).remaining, 0);
+    assert_eq!(status.advanced(Duration::from_secs(15)).overtime, 0);
+
+    let flow = TimerStatus {
+        flow_mode: true,
+        ..status.clone()
+    };
+    assert_eq!(flow.advanced(Duration::from_secs(15)).overtime, 5);
+
+    let stopwatch = TimerStatus {
+        mode: TimerMode::Stopwatch.to_string(),
+        overtime: 30,
+        ..status.clone()
+    };
+    assert_eq!(stopwatch.advanced(Duration::from_secs(15)).overtime, 45);
+
+    let paused = TimerStatus {
+        is_paused: true,
+        ..status.clone()
+    };
+    assert_eq!(paused.advanced(Duration::from_secs(4)).remaining, 10);
+
+    let grace = TimerStatus {
+        is_running: false,
+        is_idle: true,
+        grace_remaining: Some(5),
+        ..status
+    };
+    let advanced = grace.advanced(Duration::from_secs(2));
+    assert_eq!(advanced.grace_remaining, Some(3));
+    assert_eq!(advanced.remaining, 10);
 }
@@ -76,9 +76,9 @@ impl TodoTree {
         let sort_fn = |id: &Uuid| sort_keys.get(id).cloned().unwrap_or(("B".to_string(), 0));
 
         for item in items.values_mut() {
-            item.children.sort_by(|a, b| sort_fn(a).cmp(&sort_fn(b)));
+            item.children.sort_by_key(|a| sort_fn(a));
         }
-        roots.sort_by(|a, b| sort_fn(a).cmp(&sort_fn(b)));
+        roots.sort_by_key(|a| sort_fn(a));
 
         Self { items, roots }
     }
@@ -109,19 +109,20 @@ impl TodoTree {
         }
     }
 
+    #[allow(dead_code)]
     pub fn toggle_expanded(&mut self, id: Uuid) {
-        if let Some(item) = self.items.get_mut(&id) {
-            if !item.children.is_empty() {
-                item.expanded = !item.expanded;
-            }
+        if let Some(item) = self.items.get_mut(&id)
+            && !item.children.is_empty()
+        {
+            item.expanded = !item.expanded;
         }
     }
 
     pub fn expand(&mut self, id: Uuid) {
-        if let Some(item) = self.items.get_mut(&id) {
-            if !item.children.is_empty() {
-                item.expanded = true;
-            }
+        if let Some(item) = self.items.get_mut(&id)
+            && !item.children.is_empty()
+        {
+            item.expanded = true;
         }
     }
 
@@ -144,6 +145,7 @@ impl TodoTree {
         visible.get(cursor).map(|(_, item)| item.id)
     }
 
+    #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
         self.items.is_empty()
     }
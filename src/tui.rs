@@ -12,14 +12,20 @@ use ratatui::{
     widgets::{Clear, Paragraph, Widget},
 };
 use std::fmt;
+use std::time::Instant;
 
 use sqlx::SqlitePool;
+use tokio::sync::mpsc::{Receiver, error::TryRecvError};
 use uuid::Uuid;
 
 use crate::{
     client::PomoClient,
-    db,
-    timer::TimerStatus,
+    db::{self, presets::PresetRow},
+    protocol::{
+        Response,
+        messages::{ErrorCode, Push},
+    },
+    timer::{InterruptionKind, LogEvent, Preset, TimerMode, TimerStatus},
     todo::TodoTree,
     utils::{self, KeyCommand, centered_area, create_large_ascii_numbers, render_hint},
 };
@@ -28,8 +34,11 @@ const POPUP_WIDTH_PERCENT: u16 = 60;
 const POPUP_HEIGHT_PERCENT: u16 = 70;
 const TIMER_AREA_WIDTH_PERCENT: u16 = 100;
 const TIMER_AREA_HEIGHT_PERCENT: u16 = 50;
+#[allow(dead_code)]
 const HISTORY_FILE_PATH: &str = "history.json";
+#[allow(dead_code)]
 const WAYBAR_STATE_FILE_PATH: &str = "pomo_waybar_state.json";
+const EXTEND_SECS: u64 = 5 * 60;
 
 #[derive(Debug, Default, Copy, Clone, PartialEq)]
 enum AppMode {
@@ -38,6 +47,8 @@ enum AppMode {
     Input,
     Todo,
     TodoInput,
+    InterruptNote,
+    SessionNote,
 }
 
 impl fmt::Display for AppMode {
@@ -47,6 +58,8 @@ impl fmt::Display for AppMode {
             AppMode::Input => f.write_str("Input"),
             AppMode::Todo => f.write_str("Todo"),
             AppMode::TodoInput => f.write_str("TodoInput"),
+            AppMode::InterruptNote => f.write_str("InterruptNote"),
+            AppMode::SessionNote => f.write_str("SessionNote"),
         }
     }
 }
@@ -147,6 +160,10 @@ impl TaskInput {
 pub struct ServerApp {
     pomo_client: PomoClient,
     cached_status: Option<TimerStatus>,
+    /// When `cached_status` arrived; the countdown is interpolated from it
+    status_at: Instant,
+    /// Status changes pushed by the server; `None` when polling instead
+    pushes: Option<Receiver<Push>>,
     exit: bool,
     app_mode: AppMode,
     task_input: TaskInput,
@@ -160,6 +177,11 @@ pub struct ServerApp {
     active_todo_id: Option<Uuid>,
     pending_delete: Option<Uuid>,
     prev_session_id: Option<String>,
+    custom_presets: Vec<PresetRow>,
+    note_input: TaskInput,
+    interruption: Option<InterruptionKind>,
+    /// Finished session the note prompt is for
+    note_session: Option<Uuid>,
 }
 
 impl ServerApp {
@@ -167,6 +189,8 @@ impl ServerApp {
         Self {
             pomo_client,
             cached_status: None,
+            status_at: Instant::now(),
+            pushes: None,
             exit: false,
             app_mode: AppMode::default(),
             task_input: TaskInput::new(),
@@ -179,27 +203,30 @@ impl ServerApp {
             active_todo_id: None,
             pending_delete: None,
             prev_session_id: None,
+            custom_presets: Vec::new(),
+            note_input: TaskInput::new(),
+            interruption: None,
+            note_session: None,
         }
     }
     /// runs the application's main loop until the user quits
     pub async fn run(&mut self, terminal: &mut DefaultTerminal) -> anyhow::Result<()> {
+        self.reload_presets().await;
+        if self.pomo_client.supports("subscribe") {
+            match self.pomo_client.subscribe().await {
+                Ok((status, pushes)) => {
+                    self.set_status(status);
+                    self.pushes = Some(pushes);
+                }
+                Err(e) => log::warn!("Subscribing failed, polling instead: {e}"),
+            }
+        }
         while !self.exit {
             // Update cached status
-            let prev_idle = self.cached_status.as_ref().map(|s| s.is_idle);
-            match self
-                .pomo_client
-                .send_request(crate::protocol::Request::GetStatus)
-                .await
-            {
-                Ok(crate::protocol::messages::Response::Status(status)) => {
-                    // Detect session completion: was not idle, now is idle
-                    if prev_idle == Some(false) && status.is_idle {
-                        self.on_session_ended().await;
-                    }
-                    self.cached_status = Some(status);
-                }
-                Ok(_) => {}
-                Err(_) => {}
+            if self.pushes.is_some() {
+                self.apply_pushes().await;
+            } else {
+                self.poll_status().await;
             }
 
             // 1) Check for input events
@@ -213,15 +240,75 @@ impl ServerApp {
         Ok(())
     }
 
-    async fn on_session_ended(&mut self) {
-        if let (Some(todo_id), Some(pool)) = (self.active_todo_id, &self.pool) {
-            if let Ok(Some(session_id)) = db::todos::get_latest_session_id(pool).await {
-                if self.prev_session_id.as_deref() != Some(&session_id) {
-                    let _ =
-                        db::todos::link_todo_session(pool, &todo_id.to_string(), &session_id).await;
-                    self.prev_session_id = Some(session_id);
+    /// Applies the status changes and events the server pushed since the last
+    /// frame. Falls back to polling if the connection drops them.
+    async fn apply_pushes(&mut self) {
+        while let Some(pushes) = &mut self.pushes {
+            let push = match pushes.try_recv() {
+                Ok(push) => push,
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.pushes = None;
+                    break;
                 }
+            };
+            match push {
+                Push::Status { status, .. } => self.set_status(status),
+                Push::Event {
+                    event: LogEvent::Completed { .. } | LogEvent::Terminated { .. },
+                    ..
+                } => self.on_session_ended().await,
+                Push::Event { .. } => {}
+            }
+        }
+    }
+
+    /// Asks for the status, for servers that can't push it
+    async fn poll_status(&mut self) {
+        let prev_idle = self.cached_status.as_ref().map(|s| s.is_idle);
+        if let Ok(crate::protocol::messages::Response::Status(status)) = self
+            .pomo_client
+            .send_request(crate::protocol::Request::GetStatus)
+            .await
+        {
+            // Detect session completion: was not idle, now is idle
+            if prev_idle == Some(false) && status.is_idle {
+                self.on_session_ended().await;
             }
+            self.set_status(status);
+        }
+    }
+
+    fn set_status(&mut self, status: TimerStatus) {
+        self.maybe_prompt_session_note(&status);
+        self.cached_status = Some(status);
+        self.status_at = Instant::now();
+    }
+
+    async fn on_session_ended(&mut self) {
+        if let (Some(todo_id), Some(pool)) = (self.active_todo_id, &self.pool)
+            && let Ok(Some(session_id)) = db::todos::get_latest_session_id(pool).await
+            && self.prev_session_id.as_deref() != Some(&session_id)
+        {
+            let _ = db::todos::link_todo_session(pool, &todo_id.to_string(), &session_id).await;
+            self.prev_session_id = Some(session_id);
+        }
+    }
+
+    /// Offers the note prompt when a work or stopwatch session has just finished
+    fn maybe_prompt_session_note(&mut self, status: &TimerStatus) {
+        let Some(prev) = &self.cached_status else {
+            return;
+        };
+        let finished_work = prev.mode == "Work" || prev.mode == "Stopwatch";
+        if status.last_session_id.is_some()
+            && status.last_session_id != prev.last_session_id
+            && finished_work
+            && self.app_mode == AppMode::Normal
+        {
+            self.note_session = status.last_session_id;
+            self.note_input.break_input();
+            self.app_mode = AppMode::SessionNote;
         }
     }
 
@@ -253,7 +340,7 @@ impl ServerApp {
         if self.show_hint {
             let popup_area = centered_area(area, POPUP_WIDTH_PERCENT, POPUP_HEIGHT_PERCENT);
             frame.render_widget(Clear, popup_area);
-            let hint_table = render_hint();
+            let hint_table = render_hint(&self.custom_presets);
             frame.render_widget(hint_table, popup_area);
         }
 
@@ -376,6 +463,18 @@ impl ServerApp {
                     self.show_hint = false;
                     return Ok(());
                 }
+                if let KeyCode::Char(c @ '1'..='9') = key_event.code {
+                    let index = c as usize - '1' as usize;
+                    if let Some(preset) = self.custom_presets.get(index) {
+                        self.pomo_client
+                            .send_request(crate::protocol::Request::SetPreset {
+                                preset: Preset::Custom(preset.name.clone()),
+                                next_session: false,
+                            })
+                            .await?;
+                    }
+                    return Ok(());
+                }
                 if let Some(command) = KeyCommand::from_keycode(key_event.code) {
                     self.execute_command(command).await?
                 }
@@ -431,6 +530,90 @@ impl ServerApp {
                 Ok(())
             }
 
+            // Optional note for an interruption; Enter logs it, Esc drops it
+            AppMode::InterruptNote => match key_event.code {
+                KeyCode::Enter => {
+                    let note = self.note_input.confirm_task();
+                    if let Some(kind) = self.interruption.take() {
+                        let note = (!note.is_empty()).then_some(note);
+                        let _ = self
+                            .pomo_client
+                            .send_request(crate::protocol::Request::Interrupt { kind, note })
+                            .await;
+                    }
+                    self.app_mode = AppMode::Normal;
+                    Ok(())
+                }
+                KeyCode::Char(to_insert) => {
+                    self.note_input.enter_char(to_insert);
+                    Ok(())
+                }
+                KeyCode::Backspace => {
+                    self.note_input.delete_char();
+                    Ok(())
+                }
+                KeyCode::Left => {
+                    self.note_input.move_cursor_left();
+                    Ok(())
+                }
+                KeyCode::Right => {
+                    self.note_input.move_cursor_right();
+                    Ok(())
+                }
+                KeyCode::Esc => {
+                    self.interruption = None;
+                    self.note_input.break_input();
+                    self.app_mode = AppMode::Normal;
+                    Ok(())
+                }
+                _ => Ok(()),
+            },
+
+            // Post-session note: an optional leading 1-5 focus rating, then the note
+            AppMode::SessionNote => match key_event.code {
+                KeyCode::Enter => {
+                    let input = self.note_input.confirm_task();
+                    let (rating, note) = utils::parse_session_note(&input);
+                    if let Some(id) = self.note_session.take()
+                        && (rating.is_some() || note.is_some())
+                    {
+                        let _ = self
+                            .pomo_client
+                            .send_request(crate::protocol::Request::SetSessionNote {
+                                session_id: id.to_string(),
+                                note,
+                                rating,
+                            })
+                            .await;
+                    }
+                    self.app_mode = AppMode::Normal;
+                    Ok(())
+                }
+                KeyCode::Char(to_insert) => {
+                    self.note_input.enter_char(to_insert);
+                    Ok(())
+                }
+                KeyCode::Backspace => {
+                    self.note_input.delete_char();
+                    Ok(())
+                }
+                KeyCode::Left => {
+                    self.note_input.move_cursor_left();
+                    Ok(())
+                }
+                KeyCode::Right => {
+                    self.note_input.move_cursor_right();
+                    Ok(())
+                }
+                KeyCode::Esc => {
+                    self.note_session = None;
+                    self.note_input.break_input();
+                    self.app_mode = AppMode::Normal;
+                    Ok(())
+                }
+                _ => Ok(()),
+            },
+
             // Todo input mode (adding/editing todo items)
             AppMode::TodoInput => match key_event.code {
                 KeyCode::Enter => {
@@ -476,17 +659,17 @@ impl ServerApp {
         if key_event.code == KeyCode::Char('d') {
             if let Some(pending_id) = self.pending_delete.take() {
                 // Confirm: cursor still on the same item
-                if self.todo_tree.id_at_cursor(self.todo_cursor) == Some(pending_id) {
-                    if let Some(pool) = &self.pool {
-                        db::todos::delete_todo(pool, &pending_id.to_string()).await?;
-                        if self.active_todo_id == Some(pending_id) {
-                            self.active_todo_id = None;
-                        }
-                        self.reload_todos().await?;
-                        let visible_count = self.todo_tree.visible_items().len();
-                        if self.todo_cursor >= visible_count && visible_count > 0 {
-                            self.todo_cursor = visible_count - 1;
-                        }
+                if self.todo_tree.id_at_cursor(self.todo_cursor) == Some(pending_id)
+                    && let Some(pool) = &self.pool
+                {
+                    db::todos::delete_todo(pool, &pending_id.to_string()).await?;
+                    if self.active_todo_id == Some(pending_id) {
+                        self.active_todo_id = None;
+                    }
+                    self.reload_todos().await?;
+                    let visible_count = self.todo_tree.visible_items().len();
+                    if self.todo_cursor >= visible_count && visible_count > 0 {
+                        self.todo_cursor = visible_count - 1;
                     }
                 }
                 return Ok(());
@@ -502,15 +685,13 @@ impl ServerApp {
         self.pending_delete = None;
 
         match key_event.code {
-            KeyCode::Char('j') | KeyCode::Down => {
-                if visible_count > 0 && self.todo_cursor < visible_count - 1 {
-                    self.todo_cursor += 1;
-                }
+            KeyCode::Char('j') | KeyCode::Down
+                if visible_count > 0 && self.todo_cursor < visible_count - 1 =>
+            {
+                self.todo_cursor += 1;
             }
-            KeyCode::Char('k') | KeyCode::Up => {
-                if self.todo_cursor > 0 {
-                    self.todo_cursor -= 1;
-                }
+            KeyCode::Char('k') | KeyCode::Up if self.todo_cursor > 0 => {
+                self.todo_cursor -= 1;
             }
             KeyCode::Char('l') | KeyCode::Right => {
                 if let Some(id) = self.todo_tree.id_at_cursor(self.todo_cursor) {
@@ -526,47 +707,45 @@ impl ServerApp {
                 self.todo_input_action = Some(TodoInputAction::AddSibling);
                 self.app_mode = AppMode::TodoInput;
             }
-            KeyCode::Char('A') => {
-                if self.todo_tree.id_at_cursor(self.todo_cursor).is_some() {
-                    self.todo_input_action = Some(TodoInputAction::AddChild);
-                    self.app_mode = AppMode::TodoInput;
-                }
+            KeyCode::Char('A') if self.todo_tree.id_at_cursor(self.todo_cursor).is_some() => {
+                self.todo_input_action = Some(TodoInputAction::AddChild);
+                self.app_mode = AppMode::TodoInput;
             }
             KeyCode::Char('x') => {
-                if let Some(id) = self.todo_tree.id_at_cursor(self.todo_cursor) {
-                    if let Some(pool) = &self.pool {
-                        db::todos::toggle_todo_done(pool, &id.to_string()).await?;
-                        self.reload_todos().await?;
-                    }
+                if let Some(id) = self.todo_tree.id_at_cursor(self.todo_cursor)
+                    && let Some(pool) = &self.pool
+                {
+                    db::todos::toggle_todo_done(pool, &id.to_string()).await?;
+                    self.reload_todos().await?;
                 }
             }
             KeyCode::Char('p') => {
-                if let Some(id) = self.todo_tree.id_at_cursor(self.todo_cursor) {
-                    if let Some(pool) = &self.pool {
-                        db::todos::cycle_todo_priority(pool, &id.to_string()).await?;
-                        self.reload_todos().await?;
-                    }
+                if let Some(id) = self.todo_tree.id_at_cursor(self.todo_cursor)
+                    && let Some(pool) = &self.pool
+                {
+                    db::todos::cycle_todo_priority(pool, &id.to_string()).await?;
+                    self.reload_todos().await?;
                 }
             }
             KeyCode::Char('e') => {
-                if let Some(id) = self.todo_tree.id_at_cursor(self.todo_cursor) {
-                    if let Some(item) = self.todo_tree.items.get(&id) {
-                        self.todo_input.input = item.title.clone();
-                        self.todo_input.character_index = item.title.chars().count();
-                        self.todo_input_action = Some(TodoInputAction::EditTitle);
-                        self.app_mode = AppMode::TodoInput;
-                    }
+                if let Some(id) = self.todo_tree.id_at_cursor(self.todo_cursor)
+                    && let Some(item) = self.todo_tree.items.get(&id)
+                {
+                    self.todo_input.input = item.title.clone();
+                    self.todo_input.character_index = item.title.chars().count();
+                    self.todo_input_action = Some(TodoInputAction::EditTitle);
+                    self.app_mode = AppMode::TodoInput;
                 }
             }
             KeyCode::Enter => {
                 // Select todo as current task
-                if let Some(id) = self.todo_tree.id_at_cursor(self.todo_cursor) {
-                    if let Some(item) = self.todo_tree.items.get(&id) {
-                        let title = item.title.clone();
-                        let _ = self.pomo_client.set_task_name(title).await;
-                        self.active_todo_id = Some(id);
-                        self.app_mode = AppMode::Normal;
-                    }
+                if let Some(id) = self.todo_tree.id_at_cursor(self.todo_cursor)
+                    && let Some(item) = self.todo_tree.items.get(&id)
+                {
+                    let title = item.title.clone();
+                    let _ = self.pomo_client.set_task_name(title).await;
+                    self.active_todo_id = Some(id);
+                    self.app_mode = AppMode::Normal;
                 }
             }
             KeyCode::Esc | KeyCode::Char('t') => {
@@ -640,11 +819,24 @@ impl ServerApp {
         Ok(())
     }
 
+    async fn reload_presets(&mut self) {
+        if let Some(pool) = &self.pool
+            && let Ok(rows) = db::presets::get_all_presets(pool).await
+        {
+            self.custom_presets = rows;
+        }
+    }
+
     /// Executes a KeyCommand with direct dispatch for optimal performance
     async fn execute_command(&mut self, command: KeyCommand) -> anyhow::Result<()> {
         match command {
             KeyCommand::Quit => self.exit(),
-            KeyCommand::ToggleHint => self.show_hint = !self.show_hint,
+            KeyCommand::ToggleHint => {
+                self.show_hint = !self.show_hint;
+                if self.show_hint {
+                    self.reload_presets().await;
+                }
+            }
             KeyCommand::InputTask => {
                 self.app_mode = match self.app_mode {
                     AppMode::Normal => AppMode::Input,
@@ -663,6 +855,13 @@ impl ServerApp {
                     .send_request(crate::protocol::Request::Reset)
                     .await?;
             }
+            KeyCommand::Undo => {
+                if self.pomo_client.supports("undo") {
+                    self.pomo_client
+                        .send_request(crate::protocol::Request::Undo)
+                        .await?;
+                }
+            }
             KeyCommand::Toggle => {
                 if let Some(status) = &self.cached_status {
                     if status.is_paused || status.is_idle {
@@ -670,12 +869,82 @@ impl ServerApp {
                             .send_request(crate::protocol::Request::Start)
                             .await?;
                     } else {
-                        self.pomo_client
+                        let response = self
+                            .pomo_client
                             .send_request(crate::protocol::Request::Pause)
                             .await?;
+                        // Overtime can't be paused, so toggling ends the session
+                        if matches!(response, Response::Error(e) if e.code == ErrorCode::InOvertime)
+                        {
+                            self.pomo_client
+                                .send_request(crate::protocol::Request::Finish)
+                                .await?;
+                        }
                     }
                 }
             }
+            KeyCommand::ToggleAutoContinue => {
+                if let Some(status) = &self.cached_status {
+                    self.pomo_client
+                        .send_request(crate::protocol::Request::SetAutoContinue(
+                            !status.auto_continue,
+                        ))
+                        .await?;
+                }
+            }
+            KeyCommand::CancelGrace => {
+                self.pomo_client
+                    .send_request(crate::protocol::Request::CancelGrace)
+                    .await?;
+            }
+            KeyCommand::SkipGrace => {
+                self.pomo_client
+                    .send_request(crate::protocol::Request::SkipGrace)
+                    .await?;
+            }
+            KeyCommand::Extend => {
+                self.pomo_client
+                    .send_request(crate::protocol::Request::Extend(EXTEND_SECS))
+                    .await?;
+            }
+            KeyCommand::ToggleFlow => {
+                if let Some(status) = &self.cached_status {
+                    self.pomo_client
+                        .send_request(crate::protocol::Request::SetFlowMode(!status.flow_mode))
+                        .await?;
+                }
+            }
+            KeyCommand::Finish => {
+                self.pomo_client
+                    .send_request(crate::protocol::Request::Finish)
+                    .await?;
+            }
+            KeyCommand::ToggleStopwatch => {
+                if let Some(status) = &self.cached_status {
+                    let mode = if status.mode == "Stopwatch" {
+                        TimerMode::Work
+                    } else {
+                        TimerMode::Stopwatch
+                    };
+                    self.pomo_client
+                        .send_request(crate::protocol::Request::SetMode(mode))
+                        .await?;
+                }
+            }
+            KeyCommand::InterruptInternal | KeyCommand::InterruptExternal => {
+                let in_session = self
+                    .cached_status
+                    .as_ref()
+                    .is_some_and(|s| !s.is_idle && (s.mode == "Work" || s.mode == "Stopwatch"));
+                if in_session {
+                    self.interruption = Some(if command == KeyCommand::InterruptInternal {
+                        InterruptionKind::Internal
+                    } else {
+                        InterruptionKind::External
+                    });
+                    self.app_mode = AppMode::InterruptNote;
+                }
+            }
             KeyCommand::SwitchMode => {
                 self.pomo_client
                     .send_request(crate::protocol::Request::SwitchMode)
@@ -683,23 +952,26 @@ impl ServerApp {
             }
             KeyCommand::SetLong => {
                 self.pomo_client
-                    .send_request(crate::protocol::Request::SetPreset(
-                        crate::timer::Preset::Long,
-                    ))
+                    .send_request(crate::protocol::Request::SetPreset {
+                        preset: Preset::Long,
+                        next_session: false,
+                    })
                     .await?;
             }
             KeyCommand::SetShort => {
                 self.pomo_client
-                    .send_request(crate::protocol::Request::SetPreset(
-                        crate::timer::Preset::Short,
-                    ))
+                    .send_request(crate::protocol::Request::SetPreset {
+                        preset: Preset::Short,
+                        next_session: false,
+                    })
                     .await?;
             }
             KeyCommand::SetTest => {
                 self.pomo_client
-                    .send_request(crate::protocol::Request::SetPreset(
-                        crate::timer::Preset::Test,
-                    ))
+                    .send_request(crate::protocol::Request::SetPreset {
+                        preset: Preset::Test,
+                        next_session: false,
+                    })
                     .await?;
             }
         }
@@ -713,7 +985,11 @@ impl ServerApp {
 
 impl Widget for &ServerApp {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let (render_color, remaining_time, mode_text, task_name) = match &self.cached_status {
+        let status = self
+            .cached_status
+            .as_ref()
+            .map(|s| s.advanced(self.status_at.elapsed()));
+        let (render_color, remaining_time, mode_text, task_name, cycle) = match &status {
             Some(status) => {
                 let color = if status.is_paused {
                     Color::DarkGray
@@ -721,12 +997,51 @@ impl Widget for &ServerApp {
                     match status.mode.as_str() {
                         "Work" => Color::Yellow,
                         "Break" => Color::Green,
+                        "LongBreak" => Color::Cyan,
+                        "Stopwatch" => Color::Magenta,
                         _ => Color::White,
                     }
                 };
 
-                let time = utils::fmt_duration(std::time::Duration::from_secs(status.remaining));
-                (color, time, status.mode.clone(), status.task.clone())
+                let time = if status.mode == "Stopwatch" {
+                    utils::fmt_duration(std::time::Duration::from_secs(status.overtime))
+                } else if status.overtime > 0 {
+                    format!(
+                        "-{}",
+                        utils::fmt_duration(std::time::Duration::from_secs(status.overtime))
+                    )
+                } else {
+                    utils::fmt_duration(std::time::Duration::from_secs(status.remaining))
+                };
+                let cycle = utils::fmt_cycle(status.cycle_position, status.cycle_length);
+                let mode_text = match status.grace_remaining {
+                    Some(secs) => {
+                        format!("{} starts in {}s (c: cancel, n: skip)", status.mode, secs)
+                    }
+                    None if status.mode == "Stopwatch" => {
+                        format!("{} (d: done)", status.mode)
+                    }
+                    None if status.overtime > 0 => {
+                        format!("{} overtime (Space/d: done)", status.mode)
+                    }
+                    None if status.auto_continue => format!("{} [auto]", status.mode),
+                    None => status.mode.clone(),
+                };
+                let mode_text = if status.flow_mode {
+                    format!("{mode_text} [flow]")
+                } else {
+                    mode_text
+                };
+                let mode_text = if status.interruptions > 0 {
+                    format!("{mode_text} [x{}]", status.interruptions)
+                } else {
+                    mode_text
+                };
+                let mode_text = match &status.pending_preset {
+                    Some(preset) => format!("{mode_text} [next: {preset}]"),
+                    None => mode_text,
+                };
+                (color, time, mode_text, status.task.clone(), cycle)
             }
 
             None => (
@@ -734,6 +1049,7 @@ impl Widget for &ServerApp {
                 "00:00".to_string(),
                 "Connecting...".to_string(),
                 "".to_string(),
+                "".to_string(),
             ),
         };
 
@@ -745,6 +1061,18 @@ impl Widget for &ServerApp {
             Span::raw(Local::now().format("%H:%M").to_string()),
         ]);
         text.push(state_info);
+        text.push(Line::from(cycle));
+        if let Some(goal) = self.cached_status.as_ref().and_then(|s| s.goal) {
+            let color = if goal.reached {
+                Color::Green
+            } else {
+                Color::DarkGray
+            };
+            text.push(Line::from(Span::styled(
+                utils::fmt_goal(&goal),
+                Style::default().fg(color),
+            )));
+        }
 
         let task_info = match self.app_mode {
             AppMode::Input => {
@@ -756,6 +1084,29 @@ impl Widget for &ServerApp {
                     Span::styled(input_text, Style::default().fg(Color::Green)),
                 ])
             }
+            AppMode::SessionNote => {
+                let mut input_text = self.note_input.input.clone();
+                input_text.insert(self.note_input.byte_index(), '|');
+                Line::from(vec![
+                    Span::styled(
+                        "Session done. Focus 1-5 and note (Esc: skip): ",
+                        Style::default().fg(Color::Green),
+                    ),
+                    Span::styled(input_text, Style::default().fg(Color::Green)),
+                ])
+            }
+            AppMode::InterruptNote => {
+                let mut input_text = self.note_input.input.clone();
+                input_text.insert(self.note_input.byte_index(), '|');
+                let prompt = match self.interruption {
+                    Some(InterruptionKind::External) => "External interruption, note: ",
+                    _ => "Internal interruption, note: ",
+                };
+                Line::from(vec![
+                    Span::styled(prompt, Style::default().fg(Color::Red)),
+                    Span::styled(input_text, Style::default().fg(Color::Red)),
+                ])
+            }
             _ => Line::from(vec![Span::styled(
                 task_name,
                 Style::default().fg(render_color).bold(),
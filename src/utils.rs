@@ -1,5 +1,8 @@
 use std::time::Duration;
 
+use crate::db::goals::GoalProgress;
+use crate::db::presets::PresetRow;
+use crate::timer::Durations;
 use crossterm::event::KeyCode;
 use ratatui::{
     layout::{Constraint, Flex, Layout, Rect},
@@ -32,12 +35,14 @@ const DIGITS: [[&str; ASCII_ART_HEIGHT]; 10] = [
     [" ████ ", "██  ██", " █████", "    ██", " ████ "], // 9
 ];
 const COLON: [&str; ASCII_ART_HEIGHT] = ["     ", " ██  ", "     ", " ██  ", "     "];
+const MINUS: [&str; ASCII_ART_HEIGHT] = ["      ", "      ", "█████ ", "      ", "      "];
 
 fn glyph(ch: char) -> Option<&'static [&'static str; ASCII_ART_HEIGHT]> {
     // TODO: I don't get the return type here
     match ch {
         '0'..='9' => Some(&DIGITS[(ch as u8 - b'0') as usize]),
         ':' => Some(&COLON),
+        '-' => Some(&MINUS),
         _ => None,
     }
 }
@@ -82,12 +87,22 @@ pub enum KeyCommand {
     ToggleHint,
     InputTask,
     Reset,
+    Undo,
     Toggle,
     SwitchMode,
     SetLong,
     SetShort,
     SetTest,
     OpenTodo,
+    ToggleAutoContinue,
+    CancelGrace,
+    SkipGrace,
+    Extend,
+    ToggleFlow,
+    Finish,
+    ToggleStopwatch,
+    InterruptInternal,
+    InterruptExternal,
 }
 
 impl KeyCommand {
@@ -98,12 +113,22 @@ impl KeyCommand {
             KeyCode::Char('?') => Some(Self::ToggleHint),
             KeyCode::Char('i') => Some(Self::InputTask),
             KeyCode::Char('r') => Some(Self::Reset),
+            KeyCode::Char('u') => Some(Self::Undo),
             KeyCode::Char(' ') => Some(Self::Toggle),
             KeyCode::Char('s') => Some(Self::SwitchMode),
             KeyCode::Char('+') => Some(Self::SetLong),
             KeyCode::Char('-') => Some(Self::SetShort),
             KeyCode::Char('`') => Some(Self::SetTest),
             KeyCode::Char('t') => Some(Self::OpenTodo),
+            KeyCode::Char('a') => Some(Self::ToggleAutoContinue),
+            KeyCode::Char('c') => Some(Self::CancelGrace),
+            KeyCode::Char('n') => Some(Self::SkipGrace),
+            KeyCode::Char('e') => Some(Self::Extend),
+            KeyCode::Char('f') => Some(Self::ToggleFlow),
+            KeyCode::Char('d') => Some(Self::Finish),
+            KeyCode::Char('w') => Some(Self::ToggleStopwatch),
+            KeyCode::Char('x') => Some(Self::InterruptInternal),
+            KeyCode::Char('X') => Some(Self::InterruptExternal),
             _ => None,
         }
     }
@@ -113,12 +138,22 @@ impl KeyCommand {
         match self {
             Self::InputTask => "Input current task name",
             Self::Reset => "Reset timer",
+            Self::Undo => "Undo the last reset, switch or preset change",
             Self::Toggle => "Start/Pause",
             Self::SwitchMode => "Switch Work/Break",
-            Self::SetLong => "Set Long session (50/10)[m]",
-            Self::SetShort => "Set Short session (25/5)[m]",
-            Self::SetTest => "Set Test session (5/5)[s]",
+            Self::SetLong => "Set Long session (50/10/30)[m]",
+            Self::SetShort => "Set Short session (25/5/15)[m]",
+            Self::SetTest => "Set Test session (5/5/15)[s]",
             Self::OpenTodo => "Open todo list",
+            Self::ToggleAutoContinue => "Toggle auto-continue",
+            Self::CancelGrace => "Cancel the upcoming session",
+            Self::SkipGrace => "Skip the upcoming session",
+            Self::Extend => "Extend current session by 5 min",
+            Self::ToggleFlow => "Toggle flow mode (count overtime)",
+            Self::Finish => "Done: finish the overtime/stopwatch session",
+            Self::ToggleStopwatch => "Enter/leave stopwatch mode",
+            Self::InterruptInternal => "Log an internal interruption",
+            Self::InterruptExternal => "Log an external interruption",
             Self::ToggleHint => "Close Hint Page",
             Self::Quit => "Quit",
         }
@@ -129,12 +164,22 @@ impl KeyCommand {
         match self {
             Self::InputTask => "i",
             Self::Reset => "r",
+            Self::Undo => "u",
             Self::Toggle => "Space",
             Self::SwitchMode => "s",
             Self::SetLong => "+",
             Self::SetShort => "-",
             Self::SetTest => "`",
             Self::OpenTodo => "t",
+            Self::ToggleAutoContinue => "a",
+            Self::CancelGrace => "c",
+            Self::SkipGrace => "n",
+            Self::Extend => "e",
+            Self::ToggleFlow => "f",
+            Self::Finish => "d",
+            Self::ToggleStopwatch => "w",
+            Self::InterruptInternal => "x",
+            Self::InterruptExternal => "X",
             Self::ToggleHint => "?",
             Self::Quit => "q",
         }
@@ -144,24 +189,109 @@ impl KeyCommand {
     pub const ALL: &'static [Self] = &[
         Self::InputTask,
         Self::Reset,
+        Self::Undo,
         Self::Toggle,
         Self::SwitchMode,
+        Self::Extend,
+        Self::ToggleFlow,
+        Self::Finish,
+        Self::ToggleStopwatch,
+        Self::InterruptInternal,
+        Self::InterruptExternal,
         Self::SetLong,
         Self::SetShort,
         Self::SetTest,
         Self::OpenTodo,
+        Self::ToggleAutoContinue,
+        Self::CancelGrace,
+        Self::SkipGrace,
         Self::ToggleHint,
         Self::Quit,
     ];
 }
 
-/// Renders the hint table using the type-safe KeyCommand enum
-pub fn render_hint() -> Table<'static> {
-    let rows: Vec<Row> = KeyCommand::ALL
+/// Splits post-session prompt input like "4 wrote the intro" into a focus
+/// rating and a note. Either part may be missing.
+pub fn parse_session_note(input: &str) -> (Option<u8>, Option<String>) {
+    let input = input.trim();
+    let (rating, rest) = match input.split_once(char::is_whitespace) {
+        Some((first, rest)) => (first, rest),
+        None => (input, ""),
+    };
+    match rating.parse::<u8>() {
+        Ok(r @ 1..=5) => {
+            let note = rest.trim();
+            (Some(r), (!note.is_empty()).then(|| note.to_string()))
+        }
+        _ => (None, (!input.is_empty()).then(|| input.to_string())),
+    }
+}
+
+/// Custom presets are bound to the digit keys in the order they are listed
+pub const MAX_CUSTOM_PRESET_KEYS: usize = 9;
+
+/// Formats preset durations like the built-in descriptions, e.g. "(52/17/51)[m]"
+pub fn fmt_preset_durations(durs: &Durations) -> String {
+    let secs = [durs.work, durs.brk, durs.long_brk].map(|d| d.as_secs());
+    if secs.iter().all(|s| s.is_multiple_of(60)) {
+        format!("({}/{}/{})[m]", secs[0] / 60, secs[1] / 60, secs[2] / 60)
+    } else {
+        format!("({}/{}/{})[s]", secs[0], secs[1], secs[2])
+    }
+}
+
+/// Renders the Pomodoro cycle as a row of tomatoes, one per completed work session
+pub fn fmt_cycle(position: u32, length: u32) -> String {
+    (0..length.max(position))
+        .map(|i| if i < position { "🍅" } else { "·" })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+const GOAL_GAUGE_WIDTH: u64 = 20;
+
+/// Daily goal as a text gauge, e.g. "Goal [#########-----------] 5/8 sessions"
+pub fn fmt_goal(goal: &GoalProgress) -> String {
+    let filled = (goal.done.min(goal.target) * GOAL_GAUGE_WIDTH)
+        .checked_div(goal.target)
+        .unwrap_or(GOAL_GAUGE_WIDTH);
+    format!(
+        "Goal [{}{}] {}",
+        "#".repeat(filled as usize),
+        "-".repeat((GOAL_GAUGE_WIDTH - filled) as usize),
+        goal.summary()
+    )
+}
+
+/// Renders the hint table using the type-safe KeyCommand enum, followed by
+/// the user-defined presets bound to the digit keys
+pub fn render_hint(custom_presets: &[PresetRow]) -> Table<'static> {
+    let mut rows: Vec<Row> = KeyCommand::ALL
         .iter()
         .map(|cmd| Row::new([Cell::from(cmd.key_display()), Cell::from(cmd.description())]))
         .collect();
 
+    let preset_rows = custom_presets
+        .iter()
+        .take(MAX_CUSTOM_PRESET_KEYS)
+        .enumerate()
+        .map(|(i, p)| {
+            Row::new([
+                Cell::from((i + 1).to_string()),
+                Cell::from(format!(
+                    "Set {} session {}",
+                    p.name,
+                    fmt_preset_durations(&p.durations())
+                )),
+            ])
+        });
+    // Keep the built-in preset rows and the custom ones next to each other
+    let insert_at = KeyCommand::ALL
+        .iter()
+        .position(|cmd| *cmd == KeyCommand::SetTest)
+        .map_or(rows.len(), |i| i + 1);
+    rows.splice(insert_at..insert_at, preset_rows);
+
     Table::new(rows, [Constraint::Length(10), Constraint::Fill(1)])
         .block(Block::bordered().title("Hint"))
         .flex(Flex::Center)